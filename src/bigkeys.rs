@@ -0,0 +1,166 @@
+use std::io::Write;
+
+use crate::{
+    output,
+    redis_client::{RedisClient, ScanEnd, ScanFilter},
+    redis_type::RespType,
+};
+
+/// keys asked for per `SCAN` step
+const SCAN_COUNT: usize = 100;
+
+/// a progress line is printed after this many keys
+const PROGRESS_EVERY: u64 = 10_000;
+
+/// key types with the command measuring a key's size and its unit
+const SIZE_COMMANDS: &[(&str, &str, &str)] = &[
+    ("string", "STRLEN", "bytes"),
+    ("list", "LLEN", "items"),
+    ("hash", "HLEN", "fields"),
+    ("set", "SCARD", "members"),
+    ("zset", "ZCARD", "members"),
+    ("stream", "XLEN", "entries"),
+];
+
+/// totals and the biggest key of one type
+#[derive(Default)]
+struct TypeStats {
+    keys: u64,
+    total_size: u64,
+    biggest: Option<(Vec<u8>, u64)>,
+}
+
+/// `--bigkeys`: scan the whole keyspace and report the biggest key of each type,
+/// like redis-cli; read only, keys deleted while scanning are skipped
+pub fn run(client: &mut RedisClient, out: &mut dyn Write) -> anyhow::Result<()> {
+    let total_keys = match client.execute_args(&[b"DBSIZE".to_vec()])? {
+        RespType::Integers(i) => i.value().max(0) as u64,
+        _ => 0,
+    };
+    writeln!(
+        out,
+        "# Scanning the entire keyspace to find biggest keys as well as\n# average sizes per key type.\n"
+    )?;
+
+    let mut stats: Vec<TypeStats> = SIZE_COMMANDS.iter().map(|_| TypeStats::default()).collect();
+    let mut sampled = 0u64;
+    let mut key_bytes = 0u64;
+    let end = client.scan_keys(SCAN_COUNT, &ScanFilter::default(), |client, keys| {
+        let types = client.pipeline(
+            keys.iter()
+                .map(|key| RespType::command(&[b"TYPE", key]))
+                .collect(),
+        )?;
+        // keys with a known type, TYPE says `none` for keys deleted since SCAN
+        let typed: Vec<(&Vec<u8>, usize)> = keys
+            .iter()
+            .zip(&types)
+            .filter_map(|(key, key_type)| {
+                let key_type = key_type.to_string();
+                SIZE_COMMANDS
+                    .iter()
+                    .position(|(name, _, _)| *name == key_type)
+                    .map(|index| (key, index))
+            })
+            .collect();
+        let sizes = client.pipeline(
+            typed
+                .iter()
+                .map(|(key, index)| RespType::command(&[SIZE_COMMANDS[*index].1.as_bytes(), key]))
+                .collect(),
+        )?;
+
+        for ((key, index), size) in typed.into_iter().zip(sizes) {
+            // gone between TYPE and the size command
+            let RespType::Integers(size) = size else {
+                continue;
+            };
+            let size = size.value().max(0) as u64;
+            sampled += 1;
+            key_bytes += key.len() as u64;
+
+            let (name, _, unit) = SIZE_COMMANDS[index];
+            let type_stats = &mut stats[index];
+            type_stats.keys += 1;
+            type_stats.total_size += size;
+            if type_stats
+                .biggest
+                .as_ref()
+                .is_none_or(|(_, biggest)| size > *biggest)
+            {
+                writeln!(
+                    out,
+                    "[{}%] Biggest {:>6} found so far '{}' with {} {}",
+                    percent(sampled, total_keys),
+                    name,
+                    output::quote(key),
+                    size,
+                    unit
+                )?;
+                type_stats.biggest = Some((key.clone(), size));
+            }
+            if sampled.is_multiple_of(PROGRESS_EVERY) {
+                writeln!(
+                    out,
+                    "[{}%] Sampled {} keys so far",
+                    percent(sampled, total_keys),
+                    sampled
+                )?;
+            }
+        }
+
+        Ok(true)
+    })?;
+    if end == ScanEnd::Failed {
+        anyhow::bail!("SCAN failed");
+    }
+
+    writeln!(out, "\n-------- summary -------\n")?;
+    writeln!(out, "Sampled {} keys in the keyspace!", sampled)?;
+    writeln!(
+        out,
+        "Total key length in bytes is {} (avg len {:.2})\n",
+        key_bytes,
+        average(key_bytes, sampled)
+    )?;
+    for ((name, _, unit), type_stats) in SIZE_COMMANDS.iter().zip(&stats) {
+        if let Some((key, size)) = &type_stats.biggest {
+            writeln!(
+                out,
+                "Biggest {:>6} found '{}' has {} {}",
+                name,
+                output::quote(key),
+                size,
+                unit
+            )?;
+        }
+    }
+    writeln!(out)?;
+    for ((name, _, unit), type_stats) in SIZE_COMMANDS.iter().zip(&stats) {
+        writeln!(
+            out,
+            "{} {}s with {} {} ({}% of keys, avg size {:.2})",
+            type_stats.keys,
+            name,
+            type_stats.total_size,
+            unit,
+            percent(type_stats.keys, sampled),
+            average(type_stats.total_size, type_stats.keys)
+        )?;
+    }
+
+    Ok(())
+}
+
+/// `05.25` style percentage, zero when there's nothing to divide by
+fn percent(part: u64, total: u64) -> String {
+    format!("{:05.2}", average(part * 100, total).min(100.0))
+}
+
+fn average(total: u64, count: u64) -> f64 {
+    if count == 0 {
+        0.0
+    } else {
+        total as f64 / count as f64
+    }
+}
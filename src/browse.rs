@@ -0,0 +1,462 @@
+use std::time::Duration;
+
+use anyhow::anyhow;
+
+use ratatui::{
+    DefaultTerminal, Frame,
+    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Layout},
+    style::{Style, Stylize},
+    text::Line,
+    widgets::{Block, List, ListItem, ListState, Paragraph, Wrap},
+};
+
+use crate::{
+    color, interrupt,
+    output::{self, OutputOptions},
+    redis_client::{RedisClient, ScanFilter},
+    redis_type::RespType,
+};
+
+/// keys asked for per `SCAN` step
+const SCAN_COUNT: usize = 200;
+
+/// keep this many keys loaded past the selection, scanning a page per frame until there are
+const LOOK_AHEAD: usize = 100;
+
+/// wait for a key press this long once there's nothing left to scan
+const IDLE_POLL: Duration = Duration::from_millis(250);
+
+/// elements shown when a value is dumped
+const DUMP_LIMIT: usize = 100;
+
+/// the types `t` cycles through, after them the filter is off again
+const KEY_TYPES: [&str; 6] = ["string", "list", "hash", "set", "zset", "stream"];
+
+/// what the selected key is, fetched when the selection moves
+struct KeyDetails {
+    key: Vec<u8>,
+    key_type: String,
+    ttl: String,
+}
+
+/// `_browse`: SCAN keys into a list, the selected key's TYPE and TTL beside it,
+/// Enter dumps its value
+pub struct BrowseApp<'a> {
+    client: &'a mut RedisClient,
+    keys: Vec<Vec<u8>>,
+    /// next SCAN cursor, `None` once the whole keyspace was scanned
+    cursor: Option<Vec<u8>>,
+    /// substring the list is narrowed to
+    filter: String,
+    /// `/` was pressed, keys typed go to the filter
+    editing_filter: bool,
+    /// only keys of this type are scanned
+    type_filter: Option<String>,
+    /// the server rejected `SCAN ... TYPE`, keys are checked with `TYPE` one by one
+    type_per_key: bool,
+    list_state: ListState,
+    details: Option<KeyDetails>,
+    value: Option<String>,
+    value_scroll: u16,
+    status: String,
+}
+
+impl<'a> BrowseApp<'a> {
+    pub fn new(client: &'a mut RedisClient, type_filter: Option<String>) -> Self {
+        Self {
+            client,
+            keys: Vec::new(),
+            cursor: Some(b"0".to_vec()),
+            filter: String::new(),
+            editing_filter: false,
+            type_filter,
+            type_per_key: false,
+            list_state: ListState::default(),
+            details: None,
+            value: None,
+            value_scroll: 0,
+            status: String::new(),
+        }
+    }
+
+    /// take over the terminal until `q`, Esc or Ctrl-C
+    pub fn run(mut self) -> anyhow::Result<()> {
+        let mut terminal = ratatui::init();
+        let result = self.run_loop(&mut terminal);
+        ratatui::restore();
+        result
+    }
+
+    fn run_loop(&mut self, terminal: &mut DefaultTerminal) -> anyhow::Result<()> {
+        // a stray SIGINT must not exit with the terminal still in raw mode
+        let streaming = interrupt::Streaming::start();
+        while !streaming.interrupted() {
+            let scanning = self.needs_more_keys();
+            if scanning {
+                self.scan_page()?;
+            }
+            self.refresh_details()?;
+            terminal.draw(|frame| self.ui(frame))?;
+
+            // keep scanning between key presses instead of blocking on them
+            let wait = if scanning { Duration::ZERO } else { IDLE_POLL };
+            if !event::poll(wait)? {
+                continue;
+            }
+            if let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+                && !self.handle_key(key)?
+            {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// `false` when the browser should close
+    fn handle_key(&mut self, key: KeyEvent) -> anyhow::Result<bool> {
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            return Ok(false);
+        }
+        if self.editing_filter {
+            match key.code {
+                KeyCode::Char(c) => self.filter.push(c),
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                }
+                KeyCode::Enter => self.editing_filter = false,
+                KeyCode::Esc => {
+                    self.filter.clear();
+                    self.editing_filter = false;
+                }
+                _ => {}
+            }
+            let first = (!self.visible_keys().is_empty()).then_some(0);
+            self.list_state.select(first);
+            return Ok(true);
+        }
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(false),
+            KeyCode::Char('/') => self.editing_filter = true,
+            KeyCode::Char('t') => self.cycle_type_filter(),
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+            KeyCode::PageDown => self.move_selection(20),
+            KeyCode::PageUp => self.move_selection(-20),
+            KeyCode::Right => self.value_scroll = self.value_scroll.saturating_add(1),
+            KeyCode::Left => self.value_scroll = self.value_scroll.saturating_sub(1),
+            KeyCode::Enter => self.dump_value()?,
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    /// switch to the next type and scan the keyspace again from the start
+    fn cycle_type_filter(&mut self) {
+        self.type_filter = next_type_filter(self.type_filter.as_deref()).map(str::to_string);
+        self.keys.clear();
+        self.cursor = Some(b"0".to_vec());
+        self.list_state.select(None);
+        self.details = None;
+        self.value = None;
+    }
+
+    fn move_selection(&mut self, step: isize) {
+        let count = self.visible_keys().len();
+        if count == 0 {
+            return;
+        }
+        let selected = self.list_state.selected().unwrap_or(0) as isize;
+        self.list_state
+            .select(Some((selected + step).clamp(0, count as isize - 1) as usize));
+    }
+
+    /// loaded keys containing the filter
+    fn visible_keys(&self) -> Vec<&Vec<u8>> {
+        let filter = self.filter.as_bytes();
+        self.keys
+            .iter()
+            .filter(|key| filter.is_empty() || key.windows(filter.len()).any(|w| w == filter))
+            .collect()
+    }
+
+    fn selected_key(&self) -> Option<Vec<u8>> {
+        let selected = self.list_state.selected()?;
+        self.visible_keys().get(selected).map(|key| key.to_vec())
+    }
+
+    /// more keys are scanned only while the selection is close to the end of the list,
+    /// so a huge keyspace is never loaded up front
+    fn needs_more_keys(&self) -> bool {
+        self.cursor.is_some()
+            && self.visible_keys().len() < self.list_state.selected().unwrap_or(0) + LOOK_AHEAD
+    }
+
+    fn scan_page(&mut self) -> anyhow::Result<()> {
+        let Some(cursor) = self.cursor.take() else {
+            return Ok(());
+        };
+        let filter = scan_filter(self.type_filter.as_deref(), self.type_per_key);
+        let (next, mut keys) = match self.client.scan(&cursor, SCAN_COUNT, &filter)? {
+            Some(batch) => batch,
+            // `SCAN ... TYPE` needs redis 6.0, older servers reply with an error
+            None if filter.key_type.is_some() => {
+                self.type_per_key = true;
+                self.status = "SCAN has no TYPE option here, checking each key".to_string();
+                self.client
+                    .scan(&cursor, SCAN_COUNT, &ScanFilter::default())?
+                    .ok_or_else(|| anyhow!("SCAN failed"))?
+            }
+            None => anyhow::bail!("SCAN failed"),
+        };
+        if self.type_per_key
+            && let Some(key_type) = &self.type_filter
+        {
+            keys = keys_of_type(self.client, keys, key_type)?;
+        }
+        self.keys.extend(keys);
+        self.cursor = (next != b"0").then_some(next);
+        if self.list_state.selected().is_none() && !self.visible_keys().is_empty() {
+            self.list_state.select(Some(0));
+        }
+        Ok(())
+    }
+
+    /// TYPE and TTL of the selected key, the dumped value is dropped when it changes
+    fn refresh_details(&mut self) -> anyhow::Result<()> {
+        let Some(key) = self.selected_key() else {
+            self.details = None;
+            self.value = None;
+            return Ok(());
+        };
+        if self
+            .details
+            .as_ref()
+            .is_some_and(|details| details.key == key)
+        {
+            return Ok(());
+        }
+
+        let replies = self.client.pipeline(vec![
+            RespType::command(&[b"TYPE", &key]),
+            RespType::command(&[b"TTL", &key]),
+        ])?;
+        let ttl = match replies.get(1) {
+            Some(RespType::Integers(ttl)) => match ttl.value() {
+                -2 => "key does not exist".to_string(),
+                -1 => "no expiry".to_string(),
+                seconds => format!("{}s", seconds),
+            },
+            other => other.map(RespType::to_string).unwrap_or_default(),
+        };
+        self.details = Some(KeyDetails {
+            key,
+            key_type: replies.first().map(RespType::to_string).unwrap_or_default(),
+            ttl,
+        });
+        self.value = None;
+        self.value_scroll = 0;
+        Ok(())
+    }
+
+    /// the selected key's value, read with the command for its type
+    fn dump_value(&mut self) -> anyhow::Result<()> {
+        let Some(details) = &self.details else {
+            return Ok(());
+        };
+        let key = details.key.clone();
+        let last = (DUMP_LIMIT - 1).to_string().into_bytes();
+        let args: Vec<Vec<u8>> = match details.key_type.as_str() {
+            "string" => vec![b"GET".to_vec(), key],
+            "list" => vec![b"LRANGE".to_vec(), key, b"0".to_vec(), last],
+            "hash" => vec![b"HGETALL".to_vec(), key],
+            "set" => vec![b"SMEMBERS".to_vec(), key],
+            "zset" => vec![
+                b"ZRANGE".to_vec(),
+                key,
+                b"0".to_vec(),
+                last,
+                b"WITHSCORES".to_vec(),
+            ],
+            "stream" => vec![
+                b"XRANGE".to_vec(),
+                key,
+                b"-".to_vec(),
+                b"+".to_vec(),
+                b"COUNT".to_vec(),
+                DUMP_LIMIT.to_string().into_bytes(),
+            ],
+            other => {
+                self.status = format!("can't show a {} value", other);
+                return Ok(());
+            }
+        };
+
+        let reply = self.client.execute_args(&args)?;
+        let options = OutputOptions {
+            max_elements: Some(DUMP_LIMIT),
+            ..OutputOptions::default()
+        };
+        self.value = Some(color::strip(&output::render(&reply, &options)));
+        self.value_scroll = 0;
+        self.status.clear();
+        Ok(())
+    }
+
+    fn ui(&mut self, frame: &mut Frame) {
+        let [main, status] =
+            Layout::vertical([Constraint::Min(3), Constraint::Length(1)]).areas(frame.area());
+        let [list_area, side] =
+            Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)])
+                .areas(main);
+        let [details_area, value_area] =
+            Layout::vertical([Constraint::Length(4), Constraint::Min(3)]).areas(side);
+
+        let visible = self.visible_keys();
+        let mut title = format!(" Keys ({} of {} loaded", visible.len(), self.keys.len());
+        if self.cursor.is_some() {
+            title.push_str(", more to scan");
+        }
+        title.push_str(") ");
+        if let Some(key_type) = &self.type_filter {
+            title.push_str(&format!("TYPE {} ", key_type));
+        }
+        if self.editing_filter || !self.filter.is_empty() {
+            title.push_str(&format!("/{} ", self.filter));
+        }
+        let items: Vec<ListItem> = visible
+            .iter()
+            .map(|key| ListItem::new(output::quote(key)))
+            .collect();
+        let list = List::new(items)
+            .block(Block::bordered().title(title))
+            .highlight_style(Style::new().reversed())
+            .highlight_symbol("> ");
+        frame.render_stateful_widget(list, list_area, &mut self.list_state);
+
+        let details = match &self.details {
+            Some(details) => vec![
+                Line::from(format!("TYPE: {}", details.key_type)),
+                Line::from(format!("TTL:  {}", details.ttl)),
+            ],
+            None => vec![Line::from("no key selected")],
+        };
+        frame.render_widget(
+            Paragraph::new(details).block(Block::bordered().title(" Key ")),
+            details_area,
+        );
+
+        let value = self
+            .value
+            .as_deref()
+            .unwrap_or("press Enter to show the value");
+        frame.render_widget(
+            Paragraph::new(value)
+                .block(Block::bordered().title(" Value "))
+                .wrap(Wrap { trim: false })
+                .scroll((self.value_scroll, 0)),
+            value_area,
+        );
+
+        let help = if self.editing_filter {
+            "type to filter, Enter to keep, Esc to clear"
+        } else {
+            "↑/↓ move  Enter value  ←/→ scroll value  / filter  t type  q quit"
+        };
+        let line = if self.status.is_empty() {
+            help.to_string()
+        } else {
+            format!("{}  |  {}", self.status, help)
+        };
+        frame.render_widget(Paragraph::new(line).dim(), status);
+    }
+}
+
+/// the type after `current` in `KEY_TYPES`, `None` after the last one or a type
+/// not in the list
+fn next_type_filter(current: Option<&str>) -> Option<&'static str> {
+    match current {
+        None => KEY_TYPES.first().copied(),
+        Some(current) => KEY_TYPES
+            .iter()
+            .position(|key_type| *key_type == current)
+            .and_then(|index| KEY_TYPES.get(index + 1).copied()),
+    }
+}
+
+/// the type filter goes to `SCAN` unless the server can't filter by type
+fn scan_filter(type_filter: Option<&str>, type_per_key: bool) -> ScanFilter {
+    ScanFilter {
+        key_type: type_filter
+            .filter(|_| !type_per_key)
+            .map(|key_type| key_type.as_bytes().to_vec()),
+        ..ScanFilter::default()
+    }
+}
+
+/// the keys whose `TYPE` is `key_type`, asked in one pipeline
+fn keys_of_type(
+    client: &mut RedisClient,
+    keys: Vec<Vec<u8>>,
+    key_type: &str,
+) -> anyhow::Result<Vec<Vec<u8>>> {
+    if keys.is_empty() {
+        return Ok(keys);
+    }
+    let types = client.pipeline(
+        keys.iter()
+            .map(|key| RespType::command(&[b"TYPE", key]))
+            .collect(),
+    )?;
+    Ok(keys
+        .into_iter()
+        .zip(types)
+        .filter(|(_, reply)| reply.to_string() == key_type)
+        .map(|(key, _)| key)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::redis_client::scan_command;
+
+    #[test]
+    fn type_filter_cycles_through_the_types_then_off() {
+        let mut filter = None;
+        let mut seen = vec![];
+        loop {
+            filter = next_type_filter(filter);
+            match filter {
+                Some(key_type) => seen.push(key_type),
+                None => break,
+            }
+        }
+        assert_eq!(seen, KEY_TYPES);
+        assert_eq!(next_type_filter(Some("ReJSON-RL")), None);
+    }
+
+    #[test]
+    fn scan_asks_for_the_type_unless_checked_per_key() {
+        let words = |filter: &ScanFilter| -> Vec<String> {
+            scan_command(b"0", 200, filter)
+                .iter()
+                .map(|word| String::from_utf8_lossy(word).into_owned())
+                .collect()
+        };
+        assert_eq!(
+            words(&scan_filter(None, false)),
+            ["SCAN", "0", "COUNT", "200"]
+        );
+        assert_eq!(
+            words(&scan_filter(Some("hash"), false)),
+            ["SCAN", "0", "COUNT", "200", "TYPE", "hash"]
+        );
+        assert_eq!(
+            words(&scan_filter(Some("hash"), true)),
+            ["SCAN", "0", "COUNT", "200"]
+        );
+    }
+}
@@ -1,4 +1,7 @@
-use std::io::{Read, Write};
+use std::{
+    fmt,
+    io::{Read, Write},
+};
 
 // need a simple and easy struc for read bytes
 pub struct BytesBuffer {
@@ -8,6 +11,8 @@ pub struct BytesBuffer {
     w_pos: usize,
 
     capacity: usize,
+    /// capacity the buffer was created with, used to shrink back after a large reply
+    initial_capacity: usize,
     mark: Option<usize>,
     bytes: Box<[u8]>,
 }
@@ -18,6 +23,7 @@ impl BytesBuffer {
             r_pos: 0,
             w_pos: 0,
             capacity,
+            initial_capacity: capacity,
             mark: None,
             bytes: vec![0u8; capacity].into_boxed_slice(),
         }
@@ -31,8 +37,8 @@ impl BytesBuffer {
     }
 
     pub fn write_bytes(&mut self, writer: &mut impl Write) -> anyhow::Result<()> {
-        writer.write_all(&self.bytes[self.r_pos..self.w_pos])?;
-        self.r_pos = self.w_pos;
+        writer.write_all(self.readable_bytes())?;
+        self.skip_to_end();
         self.compact();
         Ok(())
     }
@@ -48,64 +54,116 @@ impl BytesBuffer {
         }
     }
 
-    pub fn get_u8(&mut self) -> u8 {
+    /// the next unread byte, `None` at w_pos instead of stale bytes from an earlier reply
+    pub fn get_u8(&mut self) -> Option<u8> {
+        if !self.has_remaining_at_least(1) {
+            return None;
+        }
         let byte = self.bytes[self.r_pos];
         self.r_pos += 1;
-        byte
+        Some(byte)
     }
 
     pub fn put_u8(&mut self, byte: u8) {
+        self.reserve(1);
         self.bytes[self.w_pos] = byte;
         self.w_pos += 1;
     }
 
     pub fn put_u8_slice(&mut self, slice: &[u8]) {
+        self.reserve(slice.len());
         self.bytes[self.w_pos..self.w_pos + slice.len()].copy_from_slice(slice);
         self.w_pos += slice.len();
     }
 
-    pub fn has_remaining(&self) -> bool {
-        self.r_pos < self.w_pos
+    /// unread bytes, without consuming them
+    pub fn readable_bytes(&self) -> &[u8] {
+        &self.bytes[self.r_pos..self.w_pos]
+    }
+
+    /// number of unread bytes
+    pub fn remaining(&self) -> usize {
+        self.w_pos - self.r_pos
+    }
+
+    pub fn has_remaining_at_least(&self, n: usize) -> bool {
+        self.remaining() >= n
+    }
+
+    /// drop everything not read yet
+    pub fn skip_to_end(&mut self) {
+        self.r_pos = self.w_pos;
     }
 
     fn slice(&self, offset: usize, length: usize) -> &[u8] {
         &self.bytes[offset..offset + length]
     }
 
+    /// the next `length` bytes, fewer when the buffer holds less
     pub fn get_slice(&mut self, length: usize) -> &[u8] {
         let old_pos = self.r_pos;
-        self.r_pos += length;
+        self.r_pos += length.min(self.remaining());
         &self.bytes[old_pos..self.r_pos]
     }
 
+    /// the bytes before the next full `until`, which is consumed but not returned;
+    /// a terminator's first byte on its own, like a lone `\r`, is kept as data
     pub fn get_slice_until(&mut self, until: &[u8]) -> &[u8] {
         // mark position if buff don't have complete data
         self.mark();
 
         let old_pos = self.r_pos;
-        let mut bytes_count = 0;
-        let mut terminator_state = 0;
-
-        while self.has_remaining() {
-            let byte = self.get_u8();
-            if until[terminator_state] == byte {
-                terminator_state += 1;
-            } else {
-                terminator_state = 0;
-                bytes_count += 1;
-            }
+        let found = self.bytes[old_pos..self.w_pos]
+            .windows(until.len())
+            .position(|window| window == until);
 
-            if terminator_state == until.len() {
-                break;
+        match found {
+            Some(bytes_count) => {
+                self.mark = None;
+                self.r_pos = old_pos + bytes_count + until.len();
+                self.slice(old_pos, bytes_count)
+            }
+            // handle incomplete data
+            None => {
+                self.reset();
+                self.slice(old_pos, self.w_pos - old_pos)
             }
         }
+    }
 
-        // handle incomplete data
-        if terminator_state != until.len() {
-            self.reset();
+    /// make room for `additional` bytes after w_pos, compacting first and growing if needed
+    pub fn reserve(&mut self, additional: usize) {
+        if self.w_pos + additional <= self.capacity {
+            return;
         }
 
-        self.slice(old_pos, bytes_count)
+        if self.mark.is_none() {
+            self.compact();
+        }
+
+        let needed = self.w_pos + additional;
+        if needed > self.capacity {
+            self.resize(needed.max(self.capacity * 2));
+        }
+    }
+
+    /// reallocate back toward the initial capacity when a large reply made the buffer
+    /// grow beyond 4x and it is now mostly empty, keeping the unread bytes
+    pub fn shrink(&mut self) {
+        let remaining = self.remaining();
+        if self.capacity <= self.initial_capacity * 4 || remaining > self.initial_capacity {
+            return;
+        }
+
+        self.compact();
+        self.resize(self.initial_capacity);
+    }
+
+    fn resize(&mut self, capacity: usize) {
+        let mut bytes = vec![0u8; capacity].into_boxed_slice();
+        bytes[..self.w_pos].copy_from_slice(&self.bytes[..self.w_pos]);
+        self.bytes = bytes;
+        self.capacity = capacity;
     }
 
     pub fn compact(&mut self) {
@@ -120,3 +178,118 @@ impl BytesBuffer {
         }
     }
 }
+
+/// positions only, the content may hold passwords
+impl fmt::Debug for BytesBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BytesBuffer")
+            .field("r_pos", &self.r_pos)
+            .field("w_pos", &self.w_pos)
+            .field("capacity", &self.capacity)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffer(bytes: &[u8]) -> BytesBuffer {
+        let mut buff = BytesBuffer::new(16);
+        buff.put_u8_slice(bytes);
+        buff
+    }
+
+    #[test]
+    fn a_lone_cr_is_data_not_a_terminator() {
+        let mut buff = buffer(b"a\rb\r\nc\r\n");
+        assert_eq!(buff.get_slice_until(b"\r\n"), b"a\rb");
+        assert_eq!(buff.get_slice_until(b"\r\n"), b"c");
+        assert_eq!(buff.remaining(), 0);
+    }
+
+    #[test]
+    fn an_incomplete_line_is_not_consumed() {
+        let mut buff = buffer(b"abc\r");
+        assert_eq!(buff.get_slice_until(b"\r\n"), b"abc\r");
+        assert_eq!(buff.remaining(), 4);
+
+        buff.put_u8(b'\n');
+        assert_eq!(buff.get_slice_until(b"\r\n"), b"abc");
+        assert_eq!(buff.remaining(), 0);
+    }
+
+    #[test]
+    fn remaining_and_skip_to_end_after_partial_reads() {
+        let mut buff = buffer(b"+OK\r\n:1");
+        assert_eq!(buff.remaining(), 7);
+        assert_eq!(buff.get_slice(3), b"+OK");
+        assert_eq!(buff.remaining(), 4);
+        assert_eq!(buff.readable_bytes(), b"\r\n:1");
+
+        buff.skip_to_end();
+        assert_eq!(buff.remaining(), 0);
+        assert!(buff.readable_bytes().is_empty());
+    }
+
+    #[test]
+    fn reading_past_the_end_stops_at_the_written_bytes() {
+        let mut buff = buffer(b"ab");
+        assert!(buff.has_remaining_at_least(2));
+        assert!(!buff.has_remaining_at_least(3));
+        assert_eq!(buff.get_slice(10), b"ab");
+        assert_eq!(buff.get_u8(), None);
+        assert_eq!(buff.get_slice(1), b"");
+
+        // stale bytes of an earlier reply are never read again
+        buff.compact();
+        buff.put_u8(b'c');
+        assert_eq!(buff.get_u8(), Some(b'c'));
+        assert_eq!(buff.get_u8(), None);
+    }
+
+    #[test]
+    fn writes_grow_the_buffer_and_keep_unread_bytes() {
+        let mut buff = buffer(b"0123456789");
+        buff.get_slice(8);
+        // fits after compacting the 8 read bytes away
+        buff.put_u8_slice(b"abcdefghij");
+        assert_eq!(buff.capacity, 16);
+        assert_eq!(buff.readable_bytes(), b"89abcdefghij");
+
+        let large = vec![b'x'; 100];
+        buff.put_u8_slice(&large);
+        assert!(buff.capacity >= 112);
+        assert_eq!(buff.remaining(), 112);
+        assert!(buff.readable_bytes().starts_with(b"89abcdefghij"));
+    }
+
+    #[test]
+    fn the_buffer_shrinks_after_a_large_reply_is_consumed() {
+        let mut buff = BytesBuffer::new(16);
+        buff.put_u8_slice(&[b'x'; 1000]);
+        assert!(buff.capacity >= 1000);
+
+        // still holding the reply
+        buff.shrink();
+        assert!(buff.capacity >= 1000);
+
+        buff.get_slice(996);
+        buff.shrink();
+        assert_eq!(buff.capacity, 16);
+        assert_eq!(buff.readable_bytes(), b"xxxx");
+    }
+
+    #[test]
+    fn bytes_are_read_from_and_written_to_streams() {
+        let mut buff = BytesBuffer::new(16);
+        let mut reader = &b"+PONG\r\n"[..];
+        assert_eq!(buff.read_bytes(&mut reader).unwrap(), 7);
+        assert_eq!(buff.readable_bytes(), b"+PONG\r\n");
+
+        let mut written = Vec::new();
+        buff.write_bytes(&mut written).unwrap();
+        assert_eq!(written, b"+PONG\r\n");
+        assert_eq!(buff.remaining(), 0);
+    }
+}
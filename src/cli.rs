@@ -0,0 +1,489 @@
+use std::{env, fs, time::Duration};
+
+use anyhow::anyhow;
+use zeroize::Zeroizing;
+
+use crate::{output::RenderHint, redis_type::ProtoVer, tls::TlsOptions, tokenizer};
+
+pub const USAGE: &str = "./rredis-cli.exe usage: ./rredis-cli.exe [--version] [-2|-3] [-c] [--debug] [--simple] [--multi] [--live-completion] [--no-arity-check] [--show-db-info] [--confirm-exit] [--latency] [--bigkeys] [--timeout <secs>] [-n <db>] [-r <count>] [-i <secs>] [--tls [--cacert <file>] [--sni <name>] [--insecure]] [--raw|--no-raw] [--group-digits] [--with-ttl] [--time] [--verbose] [--test-mode] [--max-elements <n>] [--key-cap <n>] [--render <generic|table|stream|xautoclaim|geo>] [--args-json <json>|-] [--eval <script.lua>] [--transcript <file>] [--pass-file <path>] [--askpass] [-a <password>] (host [port [password]] | redis[s]://[[user]:password@]host[:port][/db]) [[--] command [arg ...]]
+
+With a port, a single trailing word is taken as the password, put `--` before one-word commands.
+Exit codes when running a command: 0 success, 1 error reply, 2 connection or protocol failure.";
+
+/// environment variable holding the password
+const AUTH_ENV: &str = "REDISCLI_AUTH";
+
+/// parsed command line arguments
+pub struct CliArgs {
+    pub host: String,
+    pub port: u16,
+    pub password: Option<Zeroizing<String>>,
+    /// the password came from the legacy `host port password` form
+    pub positional_password: bool,
+    /// read the password from the first line of this file
+    pub pass_file: Option<String>,
+    /// prompt for the password without echoing it
+    pub askpass: bool,
+    /// record the whole session to this file
+    pub transcript: Option<String>,
+    /// run this command and exit instead of starting the REPL
+    pub command: Option<String>,
+    /// run this lua script with `EVAL` and exit
+    pub eval: Option<String>,
+    /// keys and args of the `--eval` script, separated by a `,` word
+    pub eval_args: Vec<String>,
+    /// RESP version for HELLO, RESP3 unless `-2`
+    pub proto: Option<ProtoVer>,
+    /// follow cluster MOVED and ASK redirects
+    pub cluster: bool,
+    /// print the raw protocol bytes to stderr
+    pub debug: bool,
+    /// plain stdin loop without rustyline
+    pub simple: bool,
+    /// run piped commands as one `MULTI`/`EXEC` transaction
+    pub multi: bool,
+    /// complete hash fields and set members from the server, a round trip per key
+    pub live_completion: bool,
+    /// send commands even when their argument count doesn't match `COMMAND`
+    pub no_arity_check: bool,
+    /// print the key count and memory used after connecting
+    pub show_db_info: bool,
+    /// Ctrl-C at the prompt only exits when pressed twice
+    pub confirm_exit: bool,
+    /// sample `PING` round trips until Ctrl-C instead of starting the REPL
+    pub latency: bool,
+    /// scan for the biggest key of each type instead of starting the REPL
+    pub bigkeys: bool,
+    /// plain replies for scripts, by default only when stdout isn't a terminal
+    pub raw: Option<bool>,
+    /// render integer replies with thousands separators
+    pub group_digits: bool,
+    /// show the TTL after reading a single key
+    pub with_ttl: bool,
+    /// show how long each command took
+    pub time: bool,
+    /// show the size of each reply
+    pub verbose: bool,
+    /// disposable instance: turn off flush confirmation and DEBUG warnings
+    pub test_mode: bool,
+    /// cap the number of top-level elements shown for collections
+    pub max_elements: Option<usize>,
+    /// most keys scanned for completion
+    pub key_cap: Option<usize>,
+    /// force one reply layout instead of the per command profile
+    pub render: Option<RenderHint>,
+    /// bound connecting and waiting for replies, instead of the 5s connect default
+    pub timeout: Option<Duration>,
+    /// `-n`: database to select after connecting
+    pub db: Option<u32>,
+    /// `-r`: run the one-shot command this many times, forever when negative
+    pub repeat: i64,
+    /// `-i`: pause between repeated commands
+    pub interval: Duration,
+    /// connect over TLS with these options
+    pub tls: Option<TlsOptions>,
+    /// run one command given as a JSON array of arguments, `-` reads it from stdin
+    pub args_json: Option<String>,
+}
+
+impl CliArgs {
+    /// parse arguments without the program name, `None` means print usage
+    pub fn parse(args: &[String]) -> anyhow::Result<Option<CliArgs>> {
+        let mut positional = vec![];
+        let mut command_args = vec![];
+        let mut forced_command = false;
+        let mut password = None;
+        let mut pass_file = None;
+        let mut askpass = false;
+        let mut positional_password = false;
+        let mut transcript = None;
+        let mut proto = None;
+        let mut cluster = false;
+        let mut debug = false;
+        let mut simple = false;
+        let mut multi = false;
+        let mut live_completion = false;
+        let mut no_arity_check = false;
+        let mut show_db_info = false;
+        let mut confirm_exit = false;
+        let mut group_digits = false;
+        let mut with_ttl = false;
+        let mut time = false;
+        let mut verbose = false;
+        let mut latency = false;
+        let mut bigkeys = false;
+        let mut raw = None;
+        let mut test_mode = false;
+        let mut max_elements = None;
+        let mut key_cap = None;
+        let mut eval = None;
+        let mut args_json = None;
+        let mut render = None;
+        let mut timeout = None;
+        let mut db = None;
+        let mut repeat = 1;
+        let mut interval = Duration::ZERO;
+        let mut tls = false;
+        let mut tls_options = TlsOptions::default();
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            // once the command started, everything belongs to it
+            if !command_args.is_empty() {
+                command_args.push(arg.clone());
+                continue;
+            }
+
+            match arg.as_str() {
+                "--" => {
+                    forced_command = true;
+                    command_args.extend(iter.by_ref().cloned());
+                }
+                "-a" => password = Some(Zeroizing::new(flag_value(&mut iter, arg)?)),
+                "--pass-file" => pass_file = Some(flag_value(&mut iter, arg)?),
+                "--askpass" => askpass = true,
+                "--transcript" => transcript = Some(flag_value(&mut iter, arg)?),
+                "--timeout" => {
+                    let secs = flag_value(&mut iter, arg)?;
+                    timeout = Some(
+                        secs.parse::<f64>()
+                            .ok()
+                            .filter(|secs| secs.is_finite() && *secs > 0.0)
+                            .map(Duration::from_secs_f64)
+                            .ok_or_else(|| anyhow!("invalid timeout: {}", secs))?,
+                    );
+                }
+                "-n" => {
+                    let index = flag_value(&mut iter, arg)?;
+                    db = Some(
+                        index
+                            .parse::<u32>()
+                            .map_err(|_| anyhow!("invalid database: {}", index))?,
+                    );
+                }
+                "-r" => {
+                    let count = flag_value(&mut iter, arg)?;
+                    repeat = count
+                        .parse::<i64>()
+                        .ok()
+                        .filter(|count| *count >= -1)
+                        .ok_or_else(|| anyhow!("invalid repeat count: {}", count))?;
+                }
+                "-i" => {
+                    let secs = flag_value(&mut iter, arg)?;
+                    interval = secs
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|secs| secs.is_finite() && *secs >= 0.0)
+                        .map(Duration::from_secs_f64)
+                        .ok_or_else(|| anyhow!("invalid interval: {}", secs))?;
+                }
+                "--tls" => tls = true,
+                "--cacert" => tls_options.ca_cert = Some(flag_value(&mut iter, arg)?),
+                "--sni" => tls_options.sni = Some(flag_value(&mut iter, arg)?),
+                "--insecure" => tls_options.insecure = true,
+                "-2" => proto = Some(ProtoVer::Resp2),
+                "-3" => proto = Some(ProtoVer::Resp3),
+                "-c" => cluster = true,
+                "--debug" => debug = true,
+                "--simple" => simple = true,
+                "--multi" => multi = true,
+                "--live-completion" => live_completion = true,
+                "--no-arity-check" => no_arity_check = true,
+                "--show-db-info" => show_db_info = true,
+                "--confirm-exit" => confirm_exit = true,
+                "--latency" => latency = true,
+                "--bigkeys" => bigkeys = true,
+                "--raw" => raw = Some(true),
+                "--no-raw" => raw = Some(false),
+                "--group-digits" => group_digits = true,
+                "--with-ttl" => with_ttl = true,
+                "--time" => time = true,
+                "--verbose" => verbose = true,
+                "--test-mode" => test_mode = true,
+                "--render" => {
+                    let name = flag_value(&mut iter, arg)?;
+                    render = Some(
+                        RenderHint::from_name(&name)
+                            .ok_or_else(|| anyhow!("unknown render format: {}", name))?,
+                    );
+                }
+                "--eval" => eval = Some(flag_value(&mut iter, arg)?),
+                "--args-json" => args_json = Some(flag_value(&mut iter, arg)?),
+                "--key-cap" => key_cap = Some(flag_value(&mut iter, arg)?.parse()?),
+                "--max-elements" => max_elements = Some(flag_value(&mut iter, arg)?.parse()?),
+                // host [port [password]] then command words, a second word that
+                // isn't a port starts the command so its `-2` or `-n` stay arguments
+                _ if positional.is_empty() => positional.push(arg.clone()),
+                _ if positional.len() == 1 && arg.parse::<u16>().is_ok() => {
+                    positional.push(arg.clone())
+                }
+                _ => command_args.push(arg.clone()),
+            }
+        }
+
+        let Some(host) = positional.first().cloned() else {
+            return Ok(None);
+        };
+
+        let port = positional.get(1).and_then(|p| p.parse::<u16>().ok());
+
+        // legacy `host port password` form
+        if port.is_some()
+            && command_args.len() == 1
+            && !forced_command
+            && password.is_none()
+            && eval.is_none()
+        {
+            password = command_args.pop().map(Zeroizing::new);
+            positional_password = true;
+        }
+
+        // with --eval the words are the script's keys and args
+        let eval_args = if eval.is_some() {
+            std::mem::take(&mut command_args)
+        } else {
+            Vec::new()
+        };
+
+        Ok(Some(CliArgs {
+            host,
+            port: port.unwrap_or(6379),
+            positional_password,
+            password,
+            pass_file,
+            askpass,
+            command: if command_args.is_empty() {
+                None
+            } else {
+                // quote words so arguments with spaces survive re-tokenizing
+                Some(
+                    command_args
+                        .iter()
+                        .map(|word| tokenizer::quote_arg(word))
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                )
+            },
+            eval,
+            eval_args,
+            transcript,
+            proto,
+            cluster,
+            debug,
+            simple,
+            multi,
+            live_completion,
+            no_arity_check,
+            show_db_info,
+            confirm_exit,
+            latency,
+            bigkeys,
+            raw,
+            group_digits,
+            with_ttl,
+            time,
+            verbose,
+            test_mode,
+            max_elements,
+            key_cap,
+            args_json,
+            render,
+            timeout,
+            db,
+            repeat,
+            interval,
+            tls: tls.then_some(tls_options),
+        }))
+    }
+
+    /// `EVAL <script> <numkeys> <keys...> <args...>` for `--eval`, like redis-cli
+    pub fn eval_command(&self) -> anyhow::Result<Option<Vec<Vec<u8>>>> {
+        let Some(path) = &self.eval else {
+            return Ok(None);
+        };
+        let script =
+            fs::read(path).map_err(|e| anyhow!("could not read script {}: {}", path, e))?;
+
+        let (keys, args) = match self.eval_args.iter().position(|word| word == ",") {
+            Some(comma) => (&self.eval_args[..comma], &self.eval_args[comma + 1..]),
+            None => (&self.eval_args[..], &[][..]),
+        };
+
+        let mut command = vec![
+            b"EVAL".to_vec(),
+            script,
+            keys.len().to_string().into_bytes(),
+        ];
+        command.extend(keys.iter().chain(args).map(|word| word.as_bytes().to_vec()));
+        Ok(Some(command))
+    }
+
+    /// password by precedence: `--askpass` prompt, explicit argument, `--pass-file`,
+    /// then the `REDISCLI_AUTH` environment variable
+    pub fn resolve_password(&self) -> anyhow::Result<Option<Zeroizing<String>>> {
+        if self.askpass {
+            let password = rpassword::prompt_password("Password: ")
+                .map_err(|e| anyhow!("could not read password: {}", e))?;
+            return Ok(Some(Zeroizing::new(password)));
+        }
+        if let Some(password) = &self.password {
+            // visible in `ps` and shell history
+            if self.positional_password {
+                eprintln!(
+                    "Warning: a positional password may not be safe, use --askpass, --pass-file or REDISCLI_AUTH instead"
+                );
+            }
+            return Ok(Some(password.clone()));
+        }
+
+        match &self.pass_file {
+            Some(path) => Ok(Some(read_pass_file(path)?)),
+            // like redis-cli, keeps the password out of `ps`
+            None => Ok(env::var(AUTH_ENV).ok().map(Zeroizing::new)),
+        }
+    }
+}
+
+fn flag_value<'a>(
+    iter: &mut impl Iterator<Item = &'a String>,
+    flag: &str,
+) -> anyhow::Result<String> {
+    iter.next()
+        .cloned()
+        .ok_or_else(|| anyhow!("{} requires a value", flag))
+}
+
+/// first line of the file without the trailing newline
+fn read_pass_file(path: &str) -> anyhow::Result<Zeroizing<String>> {
+    let metadata =
+        fs::metadata(path).map_err(|e| anyhow!("could not read pass file {}: {}", path, e))?;
+    warn_if_world_readable(path, &metadata);
+
+    let content = Zeroizing::new(fs::read_to_string(path)?);
+    let first_line = content.lines().next().unwrap_or("");
+    Ok(Zeroizing::new(first_line.to_string()))
+}
+
+#[cfg(unix)]
+fn warn_if_world_readable(path: &str, metadata: &fs::Metadata) {
+    use std::os::unix::fs::PermissionsExt;
+
+    if metadata.permissions().mode() & 0o004 != 0 {
+        eprintln!("Warning: pass file {} is world-readable", path);
+    }
+}
+
+#[cfg(not(unix))]
+fn warn_if_world_readable(_path: &str, _metadata: &fs::Metadata) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> CliArgs {
+        let args: Vec<String> = line.split_whitespace().map(String::from).collect();
+        CliArgs::parse(&args).unwrap().unwrap()
+    }
+
+    #[test]
+    fn no_host_prints_usage() {
+        assert!(CliArgs::parse(&[]).unwrap().is_none());
+        assert!(CliArgs::parse(&["--debug".to_string()]).unwrap().is_none());
+    }
+
+    #[test]
+    fn host_and_port() {
+        let args = parse("127.0.0.1 6380");
+        assert_eq!(args.host, "127.0.0.1");
+        assert_eq!(args.port, 6380);
+        assert!(args.command.is_none());
+        assert!(args.password.is_none());
+    }
+
+    #[test]
+    fn flags_before_the_command_are_parsed() {
+        let args = parse("-2 -n 3 --raw localhost 6379 GET k");
+        assert_eq!(args.proto, Some(ProtoVer::Resp2));
+        assert_eq!(args.db, Some(3));
+        assert_eq!(args.raw, Some(true));
+        assert_eq!(args.command.as_deref(), Some("GET k"));
+    }
+
+    #[test]
+    fn flag_like_words_after_the_command_are_arguments() {
+        let args = parse("localhost 6379 INCRBY k -2");
+        assert_eq!(args.command.as_deref(), Some("INCRBY k -2"));
+        assert_eq!(args.proto, None);
+
+        let args = parse("localhost INCRBY k -2");
+        assert_eq!(args.port, 6379);
+        assert_eq!(args.command.as_deref(), Some("INCRBY k -2"));
+        assert_eq!(args.proto, None);
+
+        let args = parse("localhost 6379 LPUSH list -n -x");
+        assert_eq!(args.command.as_deref(), Some("LPUSH list -n -x"));
+        assert_eq!(args.db, None);
+    }
+
+    #[test]
+    fn a_single_word_after_the_port_is_the_password() {
+        let args = parse("localhost 6379 secret");
+        assert_eq!(args.password.as_deref().map(String::as_str), Some("secret"));
+        assert!(args.positional_password);
+        assert!(args.command.is_none());
+
+        let args = parse("localhost 6379 -- PING");
+        assert!(args.password.is_none());
+        assert_eq!(args.command.as_deref(), Some("PING"));
+    }
+
+    #[test]
+    fn without_a_port_one_word_is_a_command() {
+        let args = parse("localhost PING");
+        assert!(args.password.is_none());
+        assert_eq!(args.command.as_deref(), Some("PING"));
+    }
+
+    #[test]
+    fn words_with_spaces_are_quoted() {
+        let args = CliArgs::parse(&[
+            "localhost".to_string(),
+            "SET".to_string(),
+            "k".to_string(),
+            "a b".to_string(),
+        ])
+        .unwrap()
+        .unwrap();
+        assert_eq!(args.command.as_deref(), Some("SET k \"a b\""));
+    }
+
+    #[test]
+    fn invalid_flag_values_are_errors() {
+        let args =
+            |line: &str| -> Vec<String> { line.split_whitespace().map(String::from).collect() };
+        assert!(CliArgs::parse(&args("-n x localhost")).is_err());
+        assert!(CliArgs::parse(&args("--timeout 0 localhost")).is_err());
+        assert!(CliArgs::parse(&args("-r -2 localhost")).is_err());
+        assert!(CliArgs::parse(&args("-n")).is_err());
+    }
+
+    #[test]
+    fn eval_takes_the_words_as_keys_and_args() {
+        let args = parse("--eval script.lua localhost 6379 k1 k2 , a1");
+        assert_eq!(args.eval_args, ["k1", "k2", ",", "a1"]);
+        assert!(args.command.is_none());
+        assert!(args.password.is_none());
+    }
+
+    #[test]
+    fn pass_file_gives_its_first_line() {
+        let path = std::env::temp_dir().join(format!("rredis_cli_pass_{}", std::process::id()));
+        fs::write(&path, "s3cret\nsecond line\n").unwrap();
+        let password = read_pass_file(path.to_str().unwrap()).unwrap();
+        let _ = fs::remove_file(&path);
+        assert_eq!(password.as_str(), "s3cret");
+
+        assert!(read_pass_file("/nonexistent/rredis_cli_pass").is_err());
+    }
+}
@@ -0,0 +1,145 @@
+use crate::output;
+
+/// one `CLIENT LIST` line, fields an older server doesn't send are left empty or zero
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClientInfo {
+    pub id: u64,
+    pub addr: String,
+    pub name: String,
+    /// seconds since the connection was made
+    pub age: u64,
+    /// seconds since the last command
+    pub idle: u64,
+    pub flags: String,
+    pub db: Option<u32>,
+    /// last command run, `cmd` in `CLIENT LIST`
+    pub cmd: String,
+}
+
+/// how `_clients` orders its table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortBy {
+    Id,
+    /// most idle first
+    Idle,
+    /// oldest connection first
+    Age,
+}
+
+impl SortBy {
+    pub fn from_name(name: &str) -> Option<SortBy> {
+        match name.to_lowercase().as_str() {
+            "id" => Some(SortBy::Id),
+            "idle" => Some(SortBy::Idle),
+            "age" => Some(SortBy::Age),
+            _ => None,
+        }
+    }
+}
+
+/// one client per line of space separated `key=value` fields, unknown fields ignored
+pub fn parse_client_list(text: &str) -> Vec<ClientInfo> {
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(parse_client)
+        .collect()
+}
+
+fn parse_client(line: &str) -> ClientInfo {
+    let mut client = ClientInfo::default();
+    for (key, value) in line
+        .split_whitespace()
+        .filter_map(|field| field.split_once('='))
+    {
+        match key {
+            "id" => client.id = value.parse().unwrap_or(0),
+            "addr" => client.addr = value.to_string(),
+            "name" => client.name = value.to_string(),
+            "age" => client.age = value.parse().unwrap_or(0),
+            "idle" => client.idle = value.parse().unwrap_or(0),
+            "flags" => client.flags = value.to_string(),
+            "db" => client.db = value.parse().ok(),
+            "cmd" => client.cmd = value.to_string(),
+            _ => {}
+        }
+    }
+    client
+}
+
+/// aligned table, ties broken by id
+pub fn format_clients(clients: &[ClientInfo], sort_by: SortBy) -> String {
+    if clients.is_empty() {
+        return "(no clients)".to_string();
+    }
+
+    let mut clients = clients.to_vec();
+    match sort_by {
+        SortBy::Id => clients.sort_by_key(|client| client.id),
+        SortBy::Idle => clients.sort_by(|a, b| b.idle.cmp(&a.idle).then(a.id.cmp(&b.id))),
+        SortBy::Age => clients.sort_by(|a, b| b.age.cmp(&a.age).then(a.id.cmp(&b.id))),
+    }
+
+    let header = ["id", "addr", "name", "age", "idle", "flags", "db", "cmd"].map(String::from);
+    let rows: Vec<Vec<String>> = std::iter::once(header.to_vec())
+        .chain(clients.iter().map(|client| {
+            vec![
+                client.id.to_string(),
+                client.addr.clone(),
+                client.name.clone(),
+                client.age.to_string(),
+                client.idle.to_string(),
+                client.flags.clone(),
+                client.db.map(|db| db.to_string()).unwrap_or_default(),
+                client.cmd.clone(),
+            ]
+        }))
+        .collect();
+    // numbers right aligned
+    output::format_columns(&rows, &[0, 3, 4, 6])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLIENT_LIST: &str = "id=3 addr=127.0.0.1:52614 laddr=127.0.0.1:6379 fd=8 name=app age=120 idle=5 flags=N db=2 sub=0 psub=0 multi=-1 cmd=get user=default\n\
+        id=7 addr=10.0.0.2:40000 fd=9 name= age=30 idle=30 flags=P db=0 cmd=subscribe\n";
+
+    #[test]
+    fn client_list_lines_are_parsed() {
+        let clients = parse_client_list(CLIENT_LIST);
+        assert_eq!(
+            clients[0],
+            ClientInfo {
+                id: 3,
+                addr: "127.0.0.1:52614".to_string(),
+                name: "app".to_string(),
+                age: 120,
+                idle: 5,
+                flags: "N".to_string(),
+                db: Some(2),
+                cmd: "get".to_string(),
+            }
+        );
+        assert_eq!(clients[1].name, "");
+        assert_eq!(clients.len(), 2);
+        assert!(parse_client_list("\n").is_empty());
+    }
+
+    #[test]
+    fn clients_are_sorted_by_the_chosen_column() {
+        let clients = parse_client_list(CLIENT_LIST);
+        let first_id = |sort_by| {
+            format_clients(&clients, sort_by)
+                .lines()
+                .nth(1)
+                .and_then(|line| line.split_whitespace().next())
+                .map(str::to_string)
+        };
+        assert_eq!(first_id(SortBy::Id).as_deref(), Some("3"));
+        assert_eq!(first_id(SortBy::Idle).as_deref(), Some("7"));
+        assert_eq!(first_id(SortBy::Age).as_deref(), Some("3"));
+        assert_eq!(SortBy::from_name("IDLE"), Some(SortBy::Idle));
+        assert_eq!(SortBy::from_name("size"), None);
+    }
+}
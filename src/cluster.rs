@@ -0,0 +1,230 @@
+use std::collections::{HashMap, hash_map::Entry};
+
+use anyhow::anyhow;
+
+use crate::{redis_client::RedisClient, redis_type::RespType};
+
+/// number of hash slots in a redis cluster
+pub const SLOT_COUNT: u16 = 16384;
+
+/// CRC16-CCITT (XMODEM), the checksum redis cluster uses for key slots
+pub fn crc16(bytes: &[u8]) -> u16 {
+    let mut crc: u16 = 0;
+    for &byte in bytes {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// the part of the key that is hashed: the content of the first `{...}` when non-empty
+pub fn hash_tag(key: &[u8]) -> &[u8] {
+    if let Some(start) = key.iter().position(|&b| b == b'{')
+        && let Some(len) = key[start + 1..].iter().position(|&b| b == b'}')
+        && len > 0
+    {
+        return &key[start + 1..start + 1 + len];
+    }
+    key
+}
+
+/// cluster slot (0-16383) of a key, honoring hash tags
+pub fn crc16_slot(key: &[u8]) -> u16 {
+    crc16(hash_tag(key)) % SLOT_COUNT
+}
+
+/// redirects followed for one command before giving up
+const MAX_REDIRECTS: usize = 5;
+
+/// a `-MOVED <slot> <host:port>` or `-ASK <slot> <host:port>` error reply
+#[derive(Debug, PartialEq, Eq)]
+pub struct Redirect {
+    /// `ASK` moves a single command during resharding, `MOVED` the whole slot
+    pub ask: bool,
+    pub slot: u16,
+    pub host: String,
+    pub port: u16,
+}
+
+impl Redirect {
+    /// the redirect in an error reply, an empty host means the node that replied
+    pub fn parse(reply: &RespType, current_host: &str) -> Option<Redirect> {
+        if !reply.is_err_type() {
+            return None;
+        }
+
+        let text = reply.to_string();
+        let mut words = text.split_whitespace();
+        let ask = match words.next()? {
+            "MOVED" => false,
+            "ASK" => true,
+            _ => return None,
+        };
+        let slot = words.next()?.parse().ok()?;
+        // ipv6 hosts have colons of their own
+        let (host, port) = words.next()?.rsplit_once(':')?;
+        Some(Redirect {
+            ask,
+            slot,
+            host: if host.is_empty() { current_host } else { host }.to_string(),
+            port: port.parse().ok()?,
+        })
+    }
+
+    fn node(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+}
+
+/// `-c`: follows MOVED and ASK redirects to the node owning a key's slot
+#[derive(Default)]
+pub struct Cluster {
+    /// connections to the other nodes by `host:port`
+    nodes: HashMap<String, RedisClient>,
+    /// owner of each slot learnt from MOVED, so later commands go there directly
+    slots: HashMap<u16, (String, u16)>,
+}
+
+impl Cluster {
+    /// run a command on the owner of `slot` when known, following redirects from there
+    pub fn execute(
+        &mut self,
+        client: &mut RedisClient,
+        args: &[Vec<u8>],
+        slot: Option<u16>,
+    ) -> anyhow::Result<RespType> {
+        let mut target = slot.and_then(|slot| self.slots.get(&slot).cloned());
+        let mut asking = false;
+
+        for _ in 0..MAX_REDIRECTS {
+            let node = match &target {
+                Some((host, port)) => self.node(client, host, *port)?,
+                None => &mut *client,
+            };
+            let result = if asking {
+                node.execute_args(&[b"ASKING".to_vec()])
+                    .and_then(|_| node.execute_args(args))
+            } else {
+                node.execute_args(args)
+            };
+            let reply = match result {
+                Ok(reply) => reply,
+                Err(e) => {
+                    // dial the node again next time
+                    if let Some((host, port)) = &target {
+                        self.nodes.remove(&format!("{}:{}", host, port));
+                    }
+                    return Err(e);
+                }
+            };
+
+            let Some(redirect) = Redirect::parse(&reply, node.address().host()) else {
+                return Ok(reply);
+            };
+            eprintln!(
+                "-> Redirected to slot [{}] located at {}",
+                redirect.slot,
+                redirect.node()
+            );
+            if !redirect.ask {
+                self.slots
+                    .insert(redirect.slot, (redirect.host.clone(), redirect.port));
+            }
+            asking = redirect.ask;
+            target = Some((redirect.host, redirect.port));
+        }
+
+        Err(anyhow!("too many cluster redirects"))
+    }
+
+    /// the connection to `host:port`, dialed with the credentials and TLS of the first node
+    fn node<'a>(
+        &'a mut self,
+        client: &'a mut RedisClient,
+        host: &str,
+        port: u16,
+    ) -> anyhow::Result<&'a mut RedisClient> {
+        let address = client.address().with_host_port(host, port);
+        if address.address() == client.address().address() {
+            return Ok(client);
+        }
+
+        match self.nodes.entry(address.address()) {
+            Entry::Occupied(entry) => Ok(entry.into_mut()),
+            Entry::Vacant(entry) => Ok(entry.insert(RedisClient::connect(address)?)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::byte_buffer::BytesBuffer;
+
+    #[test]
+    fn slots_match_redis() {
+        assert_eq!(crc16(b"123456789"), 0x31C3);
+        assert_eq!(crc16_slot(b"foo"), 12182);
+        assert_eq!(crc16_slot(b"bar"), 5061);
+        assert_eq!(crc16_slot(b"hello"), 866);
+        assert_eq!(crc16_slot(b""), 0);
+    }
+
+    #[test]
+    fn hash_tags_pick_the_hashed_part() {
+        assert_eq!(crc16_slot(b"{user1000}.following"), 3443);
+        assert_eq!(crc16_slot(b"{user1000}.followers"), 3443);
+        assert_eq!(hash_tag(b"{user1000}.following"), b"user1000");
+        // only the first tag counts
+        assert_eq!(hash_tag(b"foo{bar}{zap}"), b"bar");
+        // an empty or unclosed tag hashes the whole key
+        assert_eq!(hash_tag(b"foo{}{bar}"), b"foo{}{bar}");
+        assert_eq!(hash_tag(b"foo{bar"), b"foo{bar");
+    }
+
+    fn error(text: &[u8]) -> RespType {
+        let mut buff = BytesBuffer::new(64);
+        buff.put_u8_slice(text);
+        RespType::decode(&mut buff)
+    }
+
+    #[test]
+    fn redirects_are_parsed_from_error_replies() {
+        assert_eq!(
+            Redirect::parse(&error(b"-MOVED 3999 127.0.0.1:6381\r\n"), "localhost"),
+            Some(Redirect {
+                ask: false,
+                slot: 3999,
+                host: "127.0.0.1".to_string(),
+                port: 6381,
+            })
+        );
+        assert_eq!(
+            Redirect::parse(&error(b"-ASK 3999 :6381\r\n"), "localhost"),
+            Some(Redirect {
+                ask: true,
+                slot: 3999,
+                host: "localhost".to_string(),
+                port: 6381,
+            })
+        );
+        assert_eq!(
+            Redirect::parse(&error(b"-MOVED 1 ::1:6381\r\n"), "localhost").map(|r| r.host),
+            Some("::1".to_string())
+        );
+        assert_eq!(
+            Redirect::parse(&error(b"-ERR wrong\r\n"), "localhost"),
+            None
+        );
+        assert_eq!(
+            Redirect::parse(&error(b"+MOVED 1 a:1\r\n"), "localhost"),
+            None
+        );
+    }
+}
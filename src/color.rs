@@ -0,0 +1,81 @@
+use std::{
+    env,
+    ffi::OsStr,
+    io::{self, IsTerminal},
+    sync::OnceLock,
+};
+
+/// gray, used for inline hints
+pub const GRAY: &str = "90";
+/// bold red, used for warnings
+pub const BOLD_RED: &str = "1;31";
+/// red, used for error replies
+pub const RED: &str = "31";
+/// green, used for integer replies
+pub const GREEN: &str = "32";
+
+/// whether ANSI colors may be written: off when `NO_COLOR` is set or stdout isn't a terminal
+pub fn enabled() -> bool {
+    static ENABLED: OnceLock<bool> = OnceLock::new();
+    *ENABLED.get_or_init(|| {
+        colors_allowed(
+            env::var_os("NO_COLOR").as_deref(),
+            io::stdout().is_terminal(),
+        )
+    })
+}
+
+/// https://no-color.org: a non-empty `NO_COLOR` disables colors
+fn colors_allowed(no_color: Option<&OsStr>, is_terminal: bool) -> bool {
+    no_color.is_none_or(|v| v.is_empty()) && is_terminal
+}
+
+/// wrap text in an ANSI color, or return it bare when colors are disabled
+pub fn paint(text: &str, color: &str) -> String {
+    if enabled() {
+        format!("\x1b[{}m{}\x1b[0m", color, text)
+    } else {
+        text.to_string()
+    }
+}
+
+/// text with the color codes `paint` added removed again
+pub fn strip(text: &str) -> String {
+    let mut plain = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("\x1b[") {
+        plain.push_str(&rest[..start]);
+        rest = &rest[start..];
+        match rest.find('m') {
+            Some(end) => rest = &rest[end + 1..],
+            None => break,
+        }
+    }
+    plain.push_str(rest);
+    plain
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_color_or_a_pipe_turns_colors_off() {
+        assert!(colors_allowed(None, true));
+        assert!(!colors_allowed(Some(OsStr::new("1")), true));
+        assert!(!colors_allowed(None, false));
+    }
+
+    #[test]
+    fn an_empty_no_color_is_ignored() {
+        assert!(colors_allowed(Some(OsStr::new("")), true));
+        assert!(!colors_allowed(Some(OsStr::new("")), false));
+    }
+
+    #[test]
+    fn strip_removes_the_color_codes() {
+        assert_eq!(strip("\x1b[32m(integer) 1\x1b[0m"), "(integer) 1");
+        assert_eq!(strip("plain"), "plain");
+        assert_eq!(strip(&paint("hint", GRAY)), "hint");
+    }
+}
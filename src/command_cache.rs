@@ -1,9 +1,10 @@
-use crate::redis_client::RedisClient;
+use crate::redis_client::{RedisClient, ScanEnd, ScanFilter};
 use crate::redis_type::RespType;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::time::Instant;
 
 #[derive(Debug, Clone)]
+#[allow(unused)]
 pub struct CommandInfo {
     pub name: String,
     pub arity: i32,
@@ -14,10 +15,108 @@ pub struct CommandInfo {
     pub key_specs: Vec<KeySpec>,
     pub subcommands: Vec<String>,
     pub tips: Vec<String>,
-    pub doc_table: Vec<Vec<String>>,
+    pub summary: String,
+    pub since: String,
+    pub group: String,
+    pub arguments: Vec<ArgInfo>,
+}
+
+impl CommandInfo {
+    /// help block like redis-cli's `HELP <command>`: usage, summary, since and group
+    pub fn format_help(&self) -> String {
+        let mut usage = self.name.clone();
+        for arg in &self.arguments {
+            usage.push(' ');
+            usage.push_str(&arg.format_hint());
+        }
+
+        let mut text = format!("  {}\n  summary: {}", usage, self.summary);
+        if !self.since.is_empty() {
+            text.push_str(&format!("\n  since: {}", self.since));
+        }
+        if !self.group.is_empty() {
+            text.push_str(&format!("\n  group: {}", self.group));
+        }
+        if !self.subcommands.is_empty() {
+            let mut subcommands: Vec<String> = self
+                .subcommands
+                .iter()
+                .map(|name| name.replace('|', " ").to_uppercase())
+                .collect();
+            subcommands.sort();
+            text.push_str(&format!("\n  subcommands: {}", subcommands.join(", ")));
+        }
+        text
+    }
+}
+
+/// one argument from `COMMAND DOCS`
+#[derive(Debug, Clone)]
+pub struct ArgInfo {
+    pub name: String,
+    /// argument type: string, integer, double, key, pattern, unix-time, pure-token, oneof, block
+    pub arg_type: String,
+    pub token: Option<String>,
+    pub optional: bool,
+    pub multiple: bool,
+    /// nested arguments of `oneof` and `block` types
+    pub arguments: Vec<ArgInfo>,
+}
+
+impl ArgInfo {
+    /// display unit for numeric argument types, e.g. `timeout:int`
+    pub fn type_unit(&self) -> Option<&'static str> {
+        match self.arg_type.as_str() {
+            "integer" => Some("int"),
+            "double" => Some("float"),
+            "unix-time" => Some("unix-time"),
+            _ => None,
+        }
+    }
+
+    /// format argument as hint text, like `[EX seconds:int]` or `key [key ...]`
+    pub fn format_hint(&self) -> String {
+        let mut text = match self.arg_type.as_str() {
+            "pure-token" => self
+                .token
+                .clone()
+                .unwrap_or_else(|| self.name.to_uppercase()),
+            "oneof" => self
+                .arguments
+                .iter()
+                .map(|arg| arg.format_hint())
+                .collect::<Vec<_>>()
+                .join("|"),
+            "block" => self
+                .arguments
+                .iter()
+                .map(|arg| arg.format_hint())
+                .collect::<Vec<_>>()
+                .join(" "),
+            _ => match self.type_unit() {
+                Some(unit) => format!("{}:{}", self.name, unit),
+                None => self.name.clone(),
+            },
+        };
+
+        if self.arg_type != "pure-token"
+            && let Some(token) = &self.token
+        {
+            text = format!("{} {}", token, text);
+        }
+        if self.multiple {
+            text = format!("{} [{} ...]", text, text);
+        }
+        if self.optional {
+            text = format!("[{}]", text);
+        }
+
+        text
+    }
 }
 
 #[derive(Debug, Clone)]
+#[allow(unused)]
 pub struct KeySpec {
     pub flags: Vec<String>,
     pub start_search: KeySearch,
@@ -25,6 +124,7 @@ pub struct KeySpec {
 }
 
 #[derive(Debug, Clone)]
+#[allow(unused)]
 pub enum KeySearch {
     Index(i32),
     Keyword(String),
@@ -32,6 +132,7 @@ pub enum KeySearch {
 }
 
 #[derive(Debug, Clone)]
+#[allow(unused)]
 pub enum KeyFind {
     Range(i32, i32),
     KeyNum(i32),
@@ -39,10 +140,21 @@ pub enum KeyFind {
     Unknown,
 }
 
+/// keys kept for completion unless `--key-cap` says otherwise
+const DEFAULT_KEY_CAP: usize = 5000;
+/// `COUNT` hint of each `SCAN` call
+const SCAN_COUNT: usize = 1000;
+
 pub struct CommandCache {
     commands: HashMap<String, CommandInfo>,
     keys: Vec<String>,
     last_keys_update: Instant,
+    /// stop scanning for completion keys after this many
+    key_cap: usize,
+    /// server version the cached docs were fetched from
+    server_version: Option<String>,
+    /// number of databases, for `SELECT` completion
+    databases: Option<u32>,
 }
 
 impl CommandCache {
@@ -51,81 +163,120 @@ impl CommandCache {
             commands: HashMap::new(),
             keys: Vec::new(),
             last_keys_update: Instant::now(),
+            key_cap: DEFAULT_KEY_CAP,
+            server_version: None,
+            databases: None,
         }
     }
 
-    pub fn fetch_command_docs(&mut self, client: &mut RedisClient) -> anyhow::Result<()> {
+    /// record the version of a (re)connected server, clearing the cached
+    /// commands and keys when it differs from the one they were built against
+    pub fn track_server_version(&mut self, version: &str) -> bool {
+        let changed = self
+            .server_version
+            .as_deref()
+            .is_some_and(|cached| cached != version);
+        if changed {
+            self.commands.clear();
+            self.keys.clear();
+        }
+        self.server_version = Some(version.to_string());
+        changed
+    }
+
+    pub fn has_command_docs(&self) -> bool {
+        !self.commands.is_empty()
+    }
+
+    /// swap in commands fetched with `fetch_command_docs`
+    pub fn set_commands(&mut self, commands: HashMap<String, CommandInfo>) {
+        self.commands = commands;
+    }
+
+    /// `COMMAND` and `COMMAND DOCS` of every command, fetched without the cache so
+    /// its lock isn't held during the round trips
+    pub fn fetch_command_docs(
+        client: &mut RedisClient,
+    ) -> anyhow::Result<HashMap<String, CommandInfo>> {
+        let mut commands = HashMap::new();
         // 获取所有命令的基本信息
-        let command_list_resp = client.execute_command("COMMAND")?;
-
-        if let RespType::Arrays(commands) = command_list_resp {
-            for cmd in commands.value {
-                if let RespType::Arrays(cmd_info) = cmd {
-                    if cmd_info.value.len() >= 6 {
-                        if let RespType::BulkStrings(name_bulk) = &cmd_info.value[0] {
-                            let name = name_bulk.value.to_uppercase();
-
-                            let command_info = CommandInfo {
-                                name: name.clone(),
-                                arity: if let RespType::Integers(n) = &cmd_info.value[1] {
-                                    n.value as i32
-                                } else {
-                                    0
-                                },
-                                flags: self.extract_string_array(&cmd_info.value[2]),
-                                first_key: if let RespType::Integers(n) = &cmd_info.value[3] {
-                                    n.value as i32
-                                } else {
-                                    0
-                                },
-                                last_key: if let RespType::Integers(n) = &cmd_info.value[4] {
-                                    n.value as i32
-                                } else {
-                                    0
-                                },
-                                step: if let RespType::Integers(n) = &cmd_info.value[5] {
-                                    n.value as i32
-                                } else {
-                                    0
-                                },
-                                key_specs: Vec::new(),
-                                subcommands: Vec::new(),
-                                tips: Vec::new(),
-                                doc_table: Vec::new(),
-                            };
-
-                            self.commands.insert(name, command_info);
-                        }
-                    }
+        let command_list_resp = client.execute(&[b"COMMAND"])?;
+
+        if let RespType::Arrays(command_list) = command_list_resp {
+            for cmd in command_list.into_vec() {
+                if let RespType::Arrays(cmd_info) = cmd
+                    && cmd_info.len() >= 6
+                    && let RespType::BulkStrings(name_bulk) = &cmd_info.as_slice()[0]
+                {
+                    let name = name_bulk.value_lossy().to_uppercase();
+
+                    let command_info = CommandInfo {
+                        name: name.clone(),
+                        arity: if let RespType::Integers(n) = &cmd_info.as_slice()[1] {
+                            n.value() as i32
+                        } else {
+                            0
+                        },
+                        flags: Self::extract_string_array(&cmd_info.as_slice()[2]),
+                        first_key: if let RespType::Integers(n) = &cmd_info.as_slice()[3] {
+                            n.value() as i32
+                        } else {
+                            0
+                        },
+                        last_key: if let RespType::Integers(n) = &cmd_info.as_slice()[4] {
+                            n.value() as i32
+                        } else {
+                            0
+                        },
+                        step: if let RespType::Integers(n) = &cmd_info.as_slice()[5] {
+                            n.value() as i32
+                        } else {
+                            0
+                        },
+                        key_specs: Vec::new(),
+                        subcommands: Vec::new(),
+                        tips: Vec::new(),
+                        summary: String::new(),
+                        since: String::new(),
+                        group: String::new(),
+                        arguments: Vec::new(),
+                    };
+
+                    commands.insert(name, command_info);
                 }
             }
         }
 
         // 获取详细文档信息
-        self.fetch_detailed_docs(client)?;
+        Self::fetch_detailed_docs(client, &mut commands)?;
 
-        Ok(())
+        Ok(commands)
     }
 
-    fn fetch_detailed_docs(&mut self, client: &mut RedisClient) -> anyhow::Result<()> {
+    fn fetch_detailed_docs(
+        client: &mut RedisClient,
+        commands: &mut HashMap<String, CommandInfo>,
+    ) -> anyhow::Result<()> {
         // 对每个命令获取详细文档
-        let command_names: Vec<String> = self.commands.keys().cloned().collect();
+        let command_names: Vec<String> = commands.keys().cloned().collect();
 
         for command_name in command_names {
-            let doc_command = format!("COMMAND DOC {}", command_name);
-            match client.execute_command(&doc_command) {
+            match client.execute(&[b"COMMAND", b"DOCS", command_name.as_bytes()]) {
                 Ok(doc_resp) => {
                     // 解析文档然后更新，避免借用冲突
-                    let parsed_doc = self.parse_command_doc_response(doc_resp);
-                    if let Some(doc_info) = parsed_doc {
-                        if let Some(cmd_info) = self.commands.get_mut(&command_name) {
-                            cmd_info.doc_table = doc_info.doc_table;
-                            cmd_info.subcommands = doc_info.subcommands;
-                        }
+                    let parsed_doc = Self::parse_command_doc_response(doc_resp);
+                    if let Some(doc_info) = parsed_doc
+                        && let Some(cmd_info) = commands.get_mut(&command_name)
+                    {
+                        cmd_info.summary = doc_info.summary;
+                        cmd_info.since = doc_info.since;
+                        cmd_info.group = doc_info.group;
+                        cmd_info.arguments = doc_info.arguments;
+                        cmd_info.subcommands = doc_info.subcommands;
                     }
                 }
                 Err(_) => {
-                    // 如果COMMAND DOC不支持，跳过详细文档
+                    // 如果COMMAND DOCS不支持，跳过详细文档
                     continue;
                 }
             }
@@ -134,7 +285,7 @@ impl CommandCache {
         Ok(())
     }
 
-    fn parse_command_doc_response(&self, doc_resp: RespType) -> Option<CommandInfo> {
+    fn parse_command_doc_response(doc_resp: RespType) -> Option<CommandInfo> {
         let mut cmd_info = CommandInfo {
             name: String::new(),
             arity: 0,
@@ -145,72 +296,193 @@ impl CommandCache {
             key_specs: Vec::new(),
             subcommands: Vec::new(),
             tips: Vec::new(),
-            doc_table: Vec::new(),
+            summary: String::new(),
+            since: String::new(),
+            group: String::new(),
+            arguments: Vec::new(),
         };
 
-        self.parse_command_doc(doc_resp, &mut cmd_info);
+        Self::parse_command_doc(doc_resp, &mut cmd_info);
         Some(cmd_info)
     }
 
-    fn parse_command_doc(&self, doc_resp: RespType, cmd_info: &mut CommandInfo) {
-        // 解析COMMAND DOC的响应
-        if let RespType::Arrays(doc_data) = doc_resp {
-            if doc_data.value.len() >= 3 {
-                // doc_data通常包含: [command_name, doc_table, subcommands]
-                if let RespType::Arrays(doc_table) = &doc_data.value[1] {
-                    for row in &doc_table.value {
-                        if let RespType::Arrays(row_data) = row {
-                            let row_strings: Vec<String> = row_data
-                                .value
-                                .iter()
-                                .map(|cell| self.extract_string(cell))
-                                .collect();
-                            cmd_info.doc_table.push(row_strings);
-                        }
-                    }
-                }
+    fn parse_command_doc(doc_resp: RespType, cmd_info: &mut CommandInfo) {
+        // COMMAND DOCS <name> 返回 {name: {summary, since, group, arguments, subcommands, ...}}
+        let Some((name, doc)) = pairs(&doc_resp).into_iter().next() else {
+            return;
+        };
+        cmd_info.name = Self::extract_string(name).to_uppercase();
 
-                if let RespType::Arrays(subcommands) = &doc_data.value[2] {
-                    for subcmd in &subcommands.value {
-                        if let RespType::BulkStrings(name_bytes) = subcmd {
-                            cmd_info.subcommands.push(name_bytes.value.clone());
-                        }
-                    }
-                }
-            }
+        let string = |name| field(doc, name).map(Self::extract_string);
+        cmd_info.summary = string("summary").unwrap_or_default();
+        cmd_info.since = string("since").unwrap_or_default();
+        cmd_info.group = string("group").unwrap_or_default();
+        if let Some(arguments) = field(doc, "arguments") {
+            cmd_info.arguments = Self::parse_arguments(arguments);
+        }
+        if let Some(subcommands) = field(doc, "subcommands") {
+            cmd_info.subcommands = pairs(subcommands)
+                .into_iter()
+                .map(|(sub_name, _)| Self::extract_string(sub_name))
+                .collect();
         }
     }
 
-    pub fn update_keys(&mut self, client: &mut RedisClient) -> anyhow::Result<()> {
-        // 每30秒更新一次keys缓存
-        if self.last_keys_update.elapsed().as_secs() < 30 {
-            return Ok(());
-        }
-
-        match client.execute_command("KEYS *") {
-            Ok(keys_resp) => {
-                if let RespType::Arrays(keys_array) = keys_resp {
-                    self.keys.clear();
-                    for key in keys_array.value {
-                        if let RespType::BulkStrings(key_bytes) = key {
-                            self.keys.push(key_bytes.value.clone());
-                        }
-                    }
+    fn parse_arguments(resp: &RespType) -> Vec<ArgInfo> {
+        let RespType::Arrays(args) = resp else {
+            return Vec::new();
+        };
+
+        args.iter()
+            .map(|arg| {
+                let string = |name| field(arg, name).map(Self::extract_string);
+                let flags = field(arg, "flags")
+                    .map(Self::extract_string_array)
+                    .unwrap_or_default();
+
+                ArgInfo {
+                    name: string("name").unwrap_or_default(),
+                    arg_type: string("type").unwrap_or_default(),
+                    token: string("token"),
+                    optional: flags.iter().any(|f| f == "optional"),
+                    multiple: flags.iter().any(|f| f == "multiple"),
+                    arguments: field(arg, "arguments")
+                        .map(Self::parse_arguments)
+                        .unwrap_or_default(),
                 }
-                self.last_keys_update = Instant::now();
-            }
-            Err(_) => {
-                // 如果KEYS命令失败，保持现有keys
-            }
+            })
+            .collect()
+    }
+
+    /// the completion keys are older than 30 seconds
+    pub fn keys_stale(&self) -> bool {
+        // 每30秒更新一次keys缓存
+        self.last_keys_update.elapsed().as_secs() >= 30
+    }
+
+    /// swap in keys fetched with `fetch_keys`
+    pub fn set_keys(&mut self, keys: Vec<String>) {
+        self.keys = keys;
+        self.last_keys_update = Instant::now();
+    }
+
+    /// keys for completion with an incremental `SCAN`, stopping at `key_cap` keys so
+    /// a large keyspace never blocks the server the way `KEYS *` does;
+    /// `None` when `SCAN` failed
+    pub fn fetch_keys(
+        client: &mut RedisClient,
+        key_cap: usize,
+    ) -> anyhow::Result<Option<Vec<String>>> {
+        let mut keys = Vec::new();
+        let end = client.scan_keys(SCAN_COUNT, &ScanFilter::default(), |_, batch| {
+            keys.extend(
+                batch
+                    .iter()
+                    .map(|key| String::from_utf8_lossy(key).to_string()),
+            );
+            Ok(keys.len() < key_cap)
+        })?;
+        // 如果SCAN命令失败，保持现有keys，连接错误由调用方决定是否重连
+        if end == ScanEnd::Failed {
+            return Ok(None);
         }
 
-        Ok(())
+        keys.truncate(key_cap);
+        Ok(Some(keys))
+    }
+
+    /// database count from `CONFIG GET databases`, for `SELECT` completion
+    pub fn set_databases(&mut self, databases: Option<u32>) {
+        self.databases = databases;
+    }
+
+    /// `0` to `databases - 1`, empty when the count is unknown
+    pub fn database_indexes(&self) -> Vec<String> {
+        (0..self.databases.unwrap_or(0))
+            .map(|db| db.to_string())
+            .collect()
+    }
+
+    /// most keys kept for completion
+    pub fn set_key_cap(&mut self, key_cap: usize) {
+        self.key_cap = key_cap;
+    }
+
+    pub fn key_cap(&self) -> usize {
+        self.key_cap
     }
 
     pub fn get_command(&self, name: &str) -> Option<&CommandInfo> {
         self.commands.get(&name.to_uppercase())
     }
 
+    /// redis' `wrong number of arguments` error when `args`, the command name included,
+    /// can't match the command's arity (negative meaning at least that many);
+    /// `None` for commands not in `COMMAND`
+    pub fn arity_error(&self, args: &[Vec<u8>]) -> Option<String> {
+        let name = String::from_utf8_lossy(args.first()?);
+        let arity = self.get_command(&name)?.arity;
+        let count = args.len() as i32;
+        let matches = match arity {
+            0 => true,
+            arity if arity > 0 => count == arity,
+            arity => count >= -arity,
+        };
+        (!matches).then(|| {
+            format!(
+                "ERR wrong number of arguments for '{}' command",
+                name.to_lowercase()
+            )
+        })
+    }
+
+    /// hint for the arguments not yet typed, `typed_args` is the number of complete arguments
+    pub fn get_argument_hint(&self, command: &str, typed_args: usize) -> Option<String> {
+        let cmd_info = self.get_command(command)?;
+        let hint = cmd_info
+            .arguments
+            .iter()
+            .skip(typed_args)
+            .map(|arg| arg.format_hint())
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        if hint.is_empty() { None } else { Some(hint) }
+    }
+
+    /// whether the command only reads exactly one key, given as its first argument
+    pub fn is_single_key_read(&self, command: &str) -> bool {
+        match self.get_command(command) {
+            Some(cmd_info) => {
+                cmd_info.flags.iter().any(|f| f == "readonly")
+                    && cmd_info.first_key == 1
+                    && cmd_info.last_key == 1
+            }
+            // command docs not fetched yet
+            None => matches!(
+                command.to_uppercase().as_str(),
+                "GET"
+                    | "STRLEN"
+                    | "GETRANGE"
+                    | "HGET"
+                    | "HGETALL"
+                    | "HKEYS"
+                    | "HVALS"
+                    | "HLEN"
+                    | "LRANGE"
+                    | "LLEN"
+                    | "LINDEX"
+                    | "SMEMBERS"
+                    | "SCARD"
+                    | "ZRANGE"
+                    | "ZCARD"
+                    | "ZSCORE"
+                    | "XRANGE"
+                    | "XLEN"
+            ),
+        }
+    }
+
     pub fn get_matching_commands(&self, prefix: &str) -> Vec<String> {
         let prefix_upper = prefix.to_uppercase();
         self.commands
@@ -220,6 +492,65 @@ impl CommandCache {
             .collect()
     }
 
+    /// commands containing the input's characters in order, like `hgtall` => `HGETALL`,
+    /// best first: the earlier the match starts and the shorter the command, the better
+    pub fn get_fuzzy_commands(&self, input: &str) -> Vec<String> {
+        let input_upper = input.to_uppercase();
+        let mut scored: Vec<((usize, usize), &String)> = self
+            .commands
+            .keys()
+            .filter_map(|cmd| fuzzy_score(&input_upper, cmd).map(|score| (score, cmd)))
+            .collect();
+        scored.sort();
+        scored.into_iter().map(|(_, cmd)| cmd.clone()).collect()
+    }
+
+    /// subcommand names of a container command like `CONFIG`, e.g. `GET` for `config|get`
+    pub fn get_subcommands(&self, command: &str) -> Vec<String> {
+        let Some(info) = self.commands.get(&command.to_uppercase()) else {
+            return Vec::new();
+        };
+
+        let mut subcommands: Vec<String> = info
+            .subcommands
+            .iter()
+            .map(|full_name| {
+                let name = full_name.rsplit('|').next().unwrap_or(full_name);
+                name.to_uppercase()
+            })
+            .collect();
+        subcommands.sort();
+        subcommands
+    }
+
+    /// documented commands by group, like `string` => `[APPEND, GET, ...]`, names sorted
+    pub fn command_groups(&self) -> BTreeMap<String, Vec<String>> {
+        let mut groups: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for info in self.commands.values().filter(|info| !info.group.is_empty()) {
+            groups
+                .entry(info.group.clone())
+                .or_default()
+                .push(info.name.clone());
+        }
+        for names in groups.values_mut() {
+            names.sort();
+        }
+        groups
+    }
+
+    /// closest known command for a mistyped one, like `GTE` => `GET`
+    pub fn closest_command(&self, input: &str) -> Option<String> {
+        let input_upper = input.to_uppercase();
+        let max_distance = (input_upper.len() / 3).max(1);
+
+        self.commands
+            .keys()
+            .map(|cmd| (edit_distance(&input_upper, cmd), cmd))
+            .filter(|(distance, _)| *distance <= max_distance)
+            .min_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.cmp(b.1)))
+            .map(|(_, cmd)| cmd.clone())
+    }
+
     pub fn get_matching_keys(&self, prefix: &str) -> Vec<String> {
         self.keys
             .iter()
@@ -229,22 +560,300 @@ impl CommandCache {
     }
 
     // 辅助方法
-    fn extract_string_array(&self, resp: &RespType) -> Vec<String> {
-        if let RespType::Arrays(arr) = resp {
-            arr.value
-                .iter()
-                .map(|item| self.extract_string(item))
-                .collect()
-        } else {
-            Vec::new()
+    fn extract_string_array(resp: &RespType) -> Vec<String> {
+        match resp {
+            RespType::Arrays(arr) => arr.iter().map(Self::extract_string).collect(),
+            // RESP3 returns command flags as a set
+            RespType::Sets(set) => set.iter().map(Self::extract_string).collect(),
+            _ => Vec::new(),
         }
     }
 
-    fn extract_string(&self, resp: &RespType) -> String {
+    fn extract_string(resp: &RespType) -> String {
         match resp {
-            RespType::BulkStrings(bytes) => bytes.value.clone(),
-            RespType::SimpleStrings(s) => s.value.clone(),
+            RespType::BulkStrings(bytes) => bytes.value_lossy().to_string(),
+            RespType::SimpleStrings(s) => s.value().to_string(),
             _ => String::new(),
         }
     }
 }
+
+/// key-value pairs of a RESP3 map or a RESP2 flat array
+fn pairs(resp: &RespType) -> Vec<(&RespType, &RespType)> {
+    match resp {
+        RespType::Maps(map) => map.iter().collect(),
+        RespType::Arrays(arr) => arr
+            .as_slice()
+            .chunks_exact(2)
+            .map(|pair| (&pair[0], &pair[1]))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// the value of `name` in a RESP3 map or a RESP2 flat array
+fn field<'a>(resp: &'a RespType, name: &str) -> Option<&'a RespType> {
+    match resp {
+        RespType::Maps(map) => map.get(name),
+        _ => pairs(resp)
+            .into_iter()
+            .find(|(key, _)| key.to_string() == name)
+            .map(|(_, value)| value),
+    }
+}
+
+/// `(start of the match, length)` when all of `input` appears in `candidate` in order,
+/// lower is better
+fn fuzzy_score(input: &str, candidate: &str) -> Option<(usize, usize)> {
+    let mut start = None;
+    let mut chars = candidate.char_indices();
+    for wanted in input.chars() {
+        let (i, _) = chars.find(|(_, c)| *c == wanted)?;
+        start.get_or_insert(i);
+    }
+    Some((start.unwrap_or(0), candidate.len()))
+}
+
+/// optimal string alignment distance, a transposition of adjacent chars counts as one edit
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    d[0] = (0..=b.len()).collect();
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+
+    d[a.len()][b.len()]
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::byte_buffer::BytesBuffer;
+
+    fn decode(bytes: &[u8]) -> RespType {
+        let mut buff = BytesBuffer::new(64);
+        buff.put_u8_slice(bytes);
+        RespType::decode(&mut buff)
+    }
+
+    pub(crate) fn command(name: &str, arity: i32, flags: &[&str], keys: i32) -> CommandInfo {
+        CommandInfo {
+            name: name.to_string(),
+            arity,
+            flags: flags.iter().map(|flag| flag.to_string()).collect(),
+            first_key: keys.min(1),
+            last_key: keys,
+            step: keys.min(1),
+            key_specs: Vec::new(),
+            subcommands: Vec::new(),
+            tips: Vec::new(),
+            summary: String::new(),
+            since: String::new(),
+            group: String::new(),
+            arguments: Vec::new(),
+        }
+    }
+
+    pub(crate) fn argument(name: &str, arg_type: &str) -> ArgInfo {
+        ArgInfo {
+            name: name.to_string(),
+            arg_type: arg_type.to_string(),
+            token: None,
+            optional: false,
+            multiple: false,
+            arguments: Vec::new(),
+        }
+    }
+
+    pub(crate) fn cache(commands: Vec<CommandInfo>) -> CommandCache {
+        let mut cache = CommandCache::new();
+        cache.set_commands(
+            commands
+                .into_iter()
+                .map(|info| (info.name.clone(), info))
+                .collect(),
+        );
+        cache
+    }
+
+    fn sample() -> CommandCache {
+        cache(vec![
+            command("GET", 2, &["readonly", "fast"], 1),
+            command("GETEX", -2, &["write"], 1),
+            command("HGETALL", 2, &["readonly"], 1),
+            command("HGET", 3, &["readonly", "fast"], 1),
+            command("MSET", -3, &["write"], -1),
+            command("SET", -3, &["write"], 1),
+            command("MGET", -2, &["readonly"], -1),
+        ])
+    }
+
+    fn args(line: &str) -> Vec<Vec<u8>> {
+        line.split_whitespace()
+            .map(|arg| arg.as_bytes().to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn arity_is_checked_for_fixed_and_variadic_commands() {
+        let cache = sample();
+        assert_eq!(cache.arity_error(&args("GET k")), None);
+        assert_eq!(
+            cache.arity_error(&args("get")).as_deref(),
+            Some("ERR wrong number of arguments for 'get' command")
+        );
+        assert!(cache.arity_error(&args("GET a b")).is_some());
+        assert_eq!(cache.arity_error(&args("MSET k v")), None);
+        assert_eq!(cache.arity_error(&args("MSET k v k2 v2")), None);
+        assert!(cache.arity_error(&args("MSET k")).is_some());
+        // unknown commands are left to the server
+        assert_eq!(cache.arity_error(&args("NOPE")), None);
+    }
+
+    #[test]
+    fn a_typo_suggests_the_closest_command() {
+        let cache = sample();
+        assert_eq!(cache.closest_command("GTE").as_deref(), Some("GET"));
+        assert_eq!(cache.closest_command("hgetal").as_deref(), Some("HGETALL"));
+        assert_eq!(cache.closest_command("XYZZY"), None);
+    }
+
+    #[test]
+    fn fuzzy_completion_ranks_the_best_match_first() {
+        let cache = sample();
+        assert_eq!(
+            cache
+                .get_fuzzy_commands("hgtall")
+                .first()
+                .map(String::as_str),
+            Some("HGETALL")
+        );
+        assert_eq!(cache.get_fuzzy_commands("zzz"), Vec::<String>::new());
+
+        let mut matching = cache.get_matching_commands("get");
+        matching.sort();
+        assert_eq!(matching, ["GET", "GETEX"]);
+    }
+
+    #[test]
+    fn subcommands_come_from_the_command_docs() {
+        let mut config = command("CONFIG", -2, &[], 0);
+        config.subcommands = vec!["config|set".to_string(), "config|get".to_string()];
+        let cache = cache(vec![config]);
+        assert_eq!(cache.get_subcommands("config"), ["GET", "SET"]);
+        assert!(cache.get_subcommands("GET").is_empty());
+    }
+
+    #[test]
+    fn command_docs_are_parsed_into_hints() {
+        let reply = decode(
+            b"%1\r\n$4\r\nwait\r\n%4\r\n$7\r\nsummary\r\n$4\r\nWait\r\n$5\r\nsince\r\n$5\r\n3.0.0\r\n$5\r\ngroup\r\n$7\r\ngeneric\r\n$9\r\narguments\r\n*2\r\n%2\r\n$4\r\nname\r\n$11\r\nnumreplicas\r\n$4\r\ntype\r\n$7\r\ninteger\r\n%2\r\n$4\r\nname\r\n$7\r\ntimeout\r\n$4\r\ntype\r\n$7\r\ninteger\r\n",
+        );
+        let mut info = command("", 3, &[], 0);
+        CommandCache::parse_command_doc(reply, &mut info);
+        assert_eq!(info.name, "WAIT");
+        assert_eq!(info.group, "generic");
+
+        let cache = cache(vec![info]);
+        assert_eq!(
+            cache.get_argument_hint("wait", 0).as_deref(),
+            Some("numreplicas:int timeout:int")
+        );
+        assert_eq!(
+            cache.get_argument_hint("wait", 1).as_deref(),
+            Some("timeout:int")
+        );
+        assert_eq!(cache.get_argument_hint("wait", 2), None);
+        assert!(
+            cache
+                .get_command("WAIT")
+                .unwrap()
+                .format_help()
+                .starts_with("  WAIT numreplicas:int timeout:int\n  summary: Wait")
+        );
+        assert_eq!(cache.command_groups()["generic"], ["WAIT"]);
+    }
+
+    #[test]
+    fn hints_show_tokens_options_and_repeats() {
+        let mut expiry = argument("seconds", "integer");
+        expiry.token = Some("EX".to_string());
+        expiry.optional = true;
+        assert_eq!(expiry.format_hint(), "[EX seconds:int]");
+
+        let mut keys = argument("key", "key");
+        keys.multiple = true;
+        assert_eq!(keys.format_hint(), "key [key ...]");
+
+        let mut condition = argument("condition", "oneof");
+        let mut nx = argument("nx", "pure-token");
+        nx.token = Some("NX".to_string());
+        let mut xx = argument("xx", "pure-token");
+        xx.token = Some("XX".to_string());
+        condition.arguments = vec![nx, xx];
+        assert_eq!(condition.format_hint(), "NX|XX");
+
+        assert_eq!(argument("score", "double").type_unit(), Some("float"));
+        assert_eq!(argument("value", "string").type_unit(), None);
+    }
+
+    #[test]
+    fn single_key_reads_come_from_the_flags() {
+        let cache = sample();
+        assert!(cache.is_single_key_read("get"));
+        assert!(!cache.is_single_key_read("SET"));
+        assert!(!cache.is_single_key_read("MGET"));
+        // a short built in list before the docs are fetched
+        assert!(CommandCache::new().is_single_key_read("hgetall"));
+        assert!(!CommandCache::new().is_single_key_read("DEL"));
+    }
+
+    #[test]
+    fn a_new_server_version_clears_the_cache() {
+        let mut cache = sample();
+        cache.set_keys(vec!["user:1".to_string(), "order:1".to_string()]);
+        assert!(!cache.track_server_version("7.2.4"));
+        assert!(!cache.track_server_version("7.2.4"));
+        assert!(cache.has_command_docs());
+        assert_eq!(cache.get_matching_keys("user"), ["user:1"]);
+
+        assert!(cache.track_server_version("7.4.0"));
+        assert!(!cache.has_command_docs());
+        assert!(cache.get_matching_keys("").is_empty());
+    }
+
+    #[test]
+    fn databases_complete_from_the_server_count() {
+        let mut cache = CommandCache::new();
+        assert!(cache.database_indexes().is_empty());
+        cache.set_databases(Some(16));
+        let indexes = cache.database_indexes();
+        assert_eq!(indexes.len(), 16);
+        assert_eq!(indexes.first().map(String::as_str), Some("0"));
+        assert_eq!(indexes.last().map(String::as_str), Some("15"));
+    }
+
+    #[test]
+    fn keys_are_stale_until_set() {
+        let mut cache = CommandCache::new();
+        cache.last_keys_update = Instant::now() - std::time::Duration::from_secs(31);
+        assert!(cache.keys_stale());
+        cache.set_keys(vec![]);
+        assert!(!cache.keys_stale());
+    }
+}
@@ -0,0 +1,219 @@
+use std::io::{self, Write};
+
+use crate::command_cache::CommandCache;
+
+/// built-in usage and summary of common commands by section, shown by `help` and
+/// used for `help <command>` when the server has no `COMMAND DOCS`
+const SECTIONS: &[(&str, &[(&str, &str)])] = &[
+    (
+        "Available commands",
+        &[
+            ("GET <key>", "Get value of key"),
+            ("SET <key> <value>", "Set value of key"),
+            ("DEL <key>", "Delete key"),
+            ("KEYS <pattern>", "Find all keys matching pattern"),
+            ("EXISTS <key>", "Check if key exists"),
+            ("TYPE <key>", "Get type of key"),
+            ("TTL <key>", "Get time to live of key"),
+            ("EXPIRE <key> <seconds>", "Set expiration on key"),
+            (
+                "INFO [section]",
+                "Get information and statistics about server",
+            ),
+            ("CONFIG GET <parameter>", "Get configuration parameter"),
+            (
+                "CONFIG SET <parameter> <value>",
+                "Set configuration parameter",
+            ),
+            ("PING", "Ping server"),
+            ("FLUSHDB", "Remove all keys from current database"),
+            ("FLUSHALL", "Remove all keys from all databases"),
+        ],
+    ),
+    (
+        "Client commands",
+        &[
+            ("help [command|@group]", "Show help for a command or group"),
+            ("_transcript <file>|off", "Record the session to a file"),
+            ("_info [section]", "Show INFO sections as aligned tables"),
+            ("_slowlog [count]", "Show slow commands, slowest first"),
+            (
+                "_clients [--by idle|age]",
+                "Show connected clients as a table",
+            ),
+            ("_latency", "Measure PING round trips until Ctrl-C"),
+            ("_watch [pattern]", "Print key events as they happen"),
+            ("_browse [TYPE t]", "Browse keys, their type, TTL and value"),
+            (
+                "_scan [MATCH p] [TYPE t]",
+                "List all matching keys with SCAN",
+            ),
+            ("_slot <key>", "Compute the cluster slot of a key locally"),
+            ("_keyslot <key>", "Show the cluster slot of a key"),
+            (
+                "_sameslot <key> ...",
+                "Check whether keys hash to the same slot",
+            ),
+            (
+                "_history [-t] [n] | -c",
+                "Show the last n commands (-t with times), or clear them",
+            ),
+        ],
+    ),
+    (
+        "Hash commands",
+        &[
+            ("HGET <key> <field>", "Get value of field in hash"),
+            ("HSET <key> <field> <value>", "Set field in hash"),
+            ("HDEL <key> <field>", "Delete field from hash"),
+            ("HGETALL <key>", "Get all fields and values in hash"),
+        ],
+    ),
+    (
+        "List commands",
+        &[
+            ("LPUSH <key> <value>", "Prepend value to list"),
+            ("RPUSH <key> <value>", "Append value to list"),
+            ("LPOP <key>", "Remove and get first element"),
+            ("RPOP <key>", "Remove and get last element"),
+            ("LLEN <key>", "Get length of list"),
+        ],
+    ),
+    (
+        "Set commands",
+        &[
+            ("SADD <key> <member>", "Add member to set"),
+            ("SREM <key> <member>", "Remove member from set"),
+            ("SMEMBERS <key>", "Get all members in set"),
+            ("SCARD <key>", "Get number of members in set"),
+        ],
+    ),
+    (
+        "Sorted Set commands",
+        &[
+            ("ZADD <key> <score> <member>", "Add member to sorted set"),
+            ("ZREM <key> <member>", "Remove member from sorted set"),
+            ("ZRANGE <key> <start> <stop>", "Get range of members"),
+            ("ZCARD <key>", "Get number of members in sorted set"),
+        ],
+    ),
+];
+
+pub fn print_help(out: &mut dyn Write) -> io::Result<()> {
+    for (section, commands) in SECTIONS {
+        writeln!(out, "{}:", section)?;
+        for (usage, summary) in commands.iter() {
+            writeln!(out, "  {:<19} - {}", usage, summary)?;
+        }
+        writeln!(out)?;
+    }
+    writeln!(out, "Features:")?;
+    writeln!(out, "  - Tab completion for commands and keys")?;
+    writeln!(out, "  - Smart parameter suggestions")?;
+    writeln!(out, "  - Command history (use arrow keys)")?;
+    writeln!(out, "  - Context-aware completion")?;
+    Ok(())
+}
+
+/// the command groups from `COMMAND DOCS`, empty when the server has none
+pub fn groups_help(cache: &CommandCache) -> Option<String> {
+    let groups = cache.command_groups();
+    if groups.is_empty() {
+        return None;
+    }
+
+    let names: Vec<&str> = groups.keys().map(String::as_str).collect();
+    Some(format!(
+        "Command groups: {}\nType `help @<group>` to list a group, `help <command>` for details",
+        names.join(", ")
+    ))
+}
+
+/// `help @<group>` lists a group's commands, `help <command>` shows one command's
+/// docs from the server, falling back to the built-in summary
+pub fn command_help(cache: &CommandCache, topic: &[&str]) -> String {
+    let name = topic.join(" ");
+    if let Some(group) = name.strip_prefix('@') {
+        let groups = cache.command_groups();
+        let Some(commands) = groups.get(&group.to_lowercase()) else {
+            return format!("No command group '{}'", group);
+        };
+        return commands
+            .iter()
+            .filter_map(|command| cache.get_command(command))
+            .map(|info| format!("  {:<19} - {}", info.name, info.summary))
+            .collect::<Vec<_>>()
+            .join("\n");
+    }
+
+    if let Some(info) = cache.get_command(topic[0])
+        && !info.summary.is_empty()
+    {
+        return info.format_help();
+    }
+
+    let upper = name.to_uppercase();
+    SECTIONS
+        .iter()
+        .flat_map(|(_, commands)| commands.iter())
+        .find(|(usage, _)| {
+            let usage = usage.to_uppercase();
+            usage == upper
+                || usage
+                    .strip_prefix(&upper)
+                    .is_some_and(|rest| rest.starts_with(' '))
+        })
+        .map(|(usage, summary)| format!("  {}\n  summary: {}", usage, summary))
+        .unwrap_or_else(|| format!("No help for '{}'", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_cache::tests::{argument, cache, command};
+
+    fn documented() -> CommandCache {
+        let mut get = command("GET", 2, &["readonly", "fast"], 1);
+        get.summary = "Returns the string value of a key.".to_string();
+        get.since = "1.0.0".to_string();
+        get.group = "string".to_string();
+        get.arguments = vec![argument("key", "key")];
+        let mut lpush = command("LPUSH", -3, &["write"], 1);
+        lpush.summary = "Prepends one or more elements to a list.".to_string();
+        lpush.group = "list".to_string();
+        cache(vec![get, lpush])
+    }
+
+    #[test]
+    fn command_help_comes_from_the_docs() {
+        assert_eq!(
+            command_help(&documented(), &["get"]),
+            "  GET key\n  summary: Returns the string value of a key.\n  since: 1.0.0\n  group: string"
+        );
+    }
+
+    #[test]
+    fn command_help_falls_back_to_the_built_in_summary() {
+        let cache = CommandCache::new();
+        assert_eq!(
+            command_help(&cache, &["del"]),
+            "  DEL <key>\n  summary: Delete key"
+        );
+        assert_eq!(command_help(&cache, &["nope"]), "No help for 'nope'");
+    }
+
+    #[test]
+    fn groups_list_their_commands() {
+        let cache = documented();
+        assert_eq!(
+            groups_help(&cache).unwrap().lines().next(),
+            Some("Command groups: list, string")
+        );
+        assert_eq!(
+            command_help(&cache, &["@LIST"]),
+            "  LPUSH               - Prepends one or more elements to a list."
+        );
+        assert_eq!(command_help(&cache, &["@geo"]), "No command group 'geo'");
+        assert!(groups_help(&CommandCache::new()).is_none());
+    }
+}
@@ -0,0 +1,360 @@
+use std::{
+    collections::VecDeque,
+    fs,
+    io::{self, ErrorKind, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use chrono::{DateTime, Local};
+use rustyline::{Cmd, ConditionalEventHandler, Event, EventContext, Movement, RepeatCount};
+
+use crate::tokenizer;
+
+/// marks the run time of the command on the next line of the history file
+const TIMESTAMP_PREFIX: &str = "# ts=";
+
+/// a command and when it was run, unknown for entries from files without timestamps
+struct Entry {
+    line: String,
+    time: Option<SystemTime>,
+}
+
+/// commands typed in the session, oldest first, optionally persisted to a file
+pub struct History {
+    entries: VecDeque<Entry>,
+    max_size: usize,
+    path: Option<PathBuf>,
+}
+
+impl History {
+    /// an in-memory history keeping at most `max_size` entries
+    pub fn new(max_size: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            max_size,
+            path: None,
+        }
+    }
+
+    /// a history saved to `path`, with the entries already in it; a missing file is empty
+    pub fn open(path: &Path, max_size: usize) -> io::Result<Self> {
+        let mut history = Self::new(max_size);
+        history.path = Some(path.to_path_buf());
+
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(history),
+            Err(e) => return Err(e),
+        };
+        // `#` lines are headers, comments or the timestamp of the next command
+        let mut time = None;
+        for line in text.lines() {
+            if let Some(secs) = line.strip_prefix(TIMESTAMP_PREFIX) {
+                time = secs
+                    .parse()
+                    .ok()
+                    .map(|secs| UNIX_EPOCH + Duration::from_secs(secs));
+            } else if !line.starts_with('#') {
+                history.push(line, time.take());
+            }
+        }
+
+        Ok(history)
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// record a command run now, blank lines and commands with credentials are skipped
+    pub fn add(&mut self, line: &str) {
+        if is_sensitive(line) {
+            return;
+        }
+        self.push(line, Some(SystemTime::now()));
+    }
+
+    /// a repeat of the previous command only updates its time
+    fn push(&mut self, line: &str, time: Option<SystemTime>) {
+        let line = line.trim();
+        if line.is_empty() {
+            return;
+        }
+        if let Some(last) = self.entries.back_mut()
+            && last.line == line
+        {
+            last.time = time.or(last.time);
+            return;
+        }
+
+        self.entries.push_back(Entry {
+            line: line.to_string(),
+            time,
+        });
+        while self.entries.len() > self.max_size {
+            self.entries.pop_front();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|entry| entry.line.as_str())
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// the newest entry before `before` containing `query`, ignoring case, with its index
+    pub fn search(&self, query: &str, before: usize) -> Option<(usize, &str)> {
+        let query = query.to_lowercase();
+        self.entries
+            .iter()
+            .enumerate()
+            .take(before)
+            .rev()
+            .find(|(_, entry)| entry.line.to_lowercase().contains(&query))
+            .map(|(i, entry)| (i, entry.line.as_str()))
+    }
+
+    /// write the entries to the history file, creating it if needed, readable by the
+    /// owner only
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let mut text = String::new();
+        for entry in &self.entries {
+            if let Some(secs) = entry
+                .time
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            {
+                text.push_str(&format!("{}{}\n", TIMESTAMP_PREFIX, secs.as_secs()));
+            }
+            text.push_str(&entry.line);
+            text.push('\n');
+        }
+
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+            options.mode(0o600);
+            // a file from before is tightened too
+            if path.exists() {
+                fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+            }
+        }
+        options.open(path)?.write_all(text.as_bytes())
+    }
+}
+
+/// a line that would put a password in the history, see `tokenizer::is_sensitive`;
+/// a line with unbalanced quotes is split on blanks instead
+pub fn is_sensitive(line: &str) -> bool {
+    match tokenizer::split_args(line) {
+        Ok(args) => tokenizer::is_sensitive(&args),
+        Err(_) => tokenizer::is_sensitive(&line.split_whitespace().collect::<Vec<_>>()),
+    }
+}
+
+/// the last `count` entries (all by default), numbered from the oldest like shell history,
+/// `with_time` prefixes each with when it ran, like `[2024-01-02 15:04:05] GET foo`
+pub fn format_history(history: &History, count: Option<usize>, with_time: bool) -> String {
+    if history.is_empty() {
+        return "(empty history)".to_string();
+    }
+
+    let skip = history.len() - count.unwrap_or(usize::MAX).min(history.len());
+    let width = history.len().to_string().len();
+    history
+        .entries
+        .iter()
+        .enumerate()
+        .skip(skip)
+        .map(|(i, entry)| match (with_time, entry.time) {
+            (false, _) => format!("{:>width$}  {}", i + 1, entry.line, width = width),
+            (true, time) => format!(
+                "{:>width$}  [{:<19}] {}",
+                i + 1,
+                time.map_or("unknown".to_string(), |time| {
+                    DateTime::<Local>::from(time)
+                        .format("%Y-%m-%d %H:%M:%S")
+                        .to_string()
+                }),
+                entry.line,
+                width = width
+            ),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Ctrl-R: replace the line with the newest command containing the typed text,
+/// pressing it again goes on to older matches
+pub struct HistorySearch {
+    history: Arc<Mutex<History>>,
+    /// the typed text, the index of the last match and the line it put in the editor
+    last: Mutex<Option<(String, usize, String)>>,
+}
+
+impl HistorySearch {
+    pub fn new(history: Arc<Mutex<History>>) -> Self {
+        Self {
+            history,
+            last: Mutex::new(None),
+        }
+    }
+}
+
+impl ConditionalEventHandler for HistorySearch {
+    fn handle(&self, _: &Event, _: RepeatCount, _: bool, ctx: &EventContext) -> Option<Cmd> {
+        let history = self.history.lock().unwrap();
+        let mut last = self.last.lock().unwrap();
+
+        // keep cycling while the line is still the previous match
+        let (query, before) = match last.take() {
+            Some((query, index, shown)) if shown == ctx.line() => (query, index),
+            _ => (ctx.line().to_string(), history.len()),
+        };
+        let Some((index, entry)) = history.search(&query, before) else {
+            *last = Some((query, before, ctx.line().to_string()));
+            return Some(Cmd::Noop);
+        };
+
+        *last = Some((query, index, entry.to_string()));
+        Some(Cmd::Replace(Movement::WholeLine, Some(entry.to_string())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a history file path of its own for each test
+    fn temp_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "rredis_cli_history_{}_{}",
+            std::process::id(),
+            name
+        ));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn saved_history_loads_back() {
+        let path = temp_path("round_trip");
+        let mut history = History::open(&path, 10).unwrap();
+        assert!(history.is_empty());
+        history.add("SET key \"a b\"");
+        history.add("GET key");
+        history.save().unwrap();
+
+        let loaded = History::open(&path, 10).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            loaded.entries().collect::<Vec<_>>(),
+            ["SET key \"a b\"", "GET key"]
+        );
+        assert!(loaded.entries.iter().all(|entry| entry.time.is_some()));
+    }
+
+    #[test]
+    fn files_without_timestamps_load_with_unknown_times() {
+        let path = temp_path("plain");
+        fs::write(&path, "# header\nPING\n# ts=1700000000\nGET key\n").unwrap();
+
+        let history = History::open(&path, 10).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(history.entries().collect::<Vec<_>>(), ["PING", "GET key"]);
+        assert_eq!(history.entries[0].time, None);
+        assert_eq!(
+            history.entries[1].time,
+            Some(UNIX_EPOCH + Duration::from_secs(1700000000))
+        );
+    }
+
+    #[test]
+    fn a_missing_file_is_an_empty_history() {
+        let history = History::open(&temp_path("missing"), 10).unwrap();
+        assert!(history.is_empty());
+    }
+
+    #[test]
+    fn consecutive_repeats_are_recorded_once() {
+        let mut history = History::new(10);
+        history.add("GET key");
+        history.add("  GET key ");
+        history.add("");
+        history.add("PING");
+        history.add("GET key");
+        assert_eq!(
+            history.entries().collect::<Vec<_>>(),
+            ["GET key", "PING", "GET key"]
+        );
+    }
+
+    #[test]
+    fn the_oldest_entries_are_dropped_past_the_max_size() {
+        let mut history = History::new(2);
+        for line in ["A", "B", "C"] {
+            history.add(line);
+        }
+        assert_eq!(history.entries().collect::<Vec<_>>(), ["B", "C"]);
+        assert_eq!(history.search("b", history.len()), Some((0, "B")));
+        assert_eq!(history.search("b", 0), None);
+    }
+
+    #[test]
+    fn format_history_numbers_the_last_entries() {
+        let mut history = History::new(20);
+        assert_eq!(format_history(&history, None, false), "(empty history)");
+        for i in 1..=10 {
+            history.add(&format!("GET {}", i));
+        }
+        assert_eq!(
+            format_history(&history, Some(2), false),
+            " 9  GET 9\n10  GET 10"
+        );
+    }
+
+    #[test]
+    fn commands_with_credentials_are_not_recorded() {
+        let mut history = History::new(10);
+        history.add("AUTH secret");
+        history.add("hello 3 auth default secret");
+        history.add("GET key");
+        assert_eq!(history.entries().collect::<Vec<_>>(), ["GET key"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn the_history_file_is_readable_by_the_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = temp_path("mode");
+        fs::write(&path, "GET old\n").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+
+        let mut history = History::open(&path, 10).unwrap();
+        history.add("GET new");
+        history.save().unwrap();
+
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
@@ -0,0 +1,93 @@
+use std::collections::BTreeMap;
+
+use crate::output;
+
+/// split an `INFO` reply into sections by their `# Section` headers, each a
+/// `field -> value` map; lines before the first header are ignored
+pub fn parse_info(text: &str) -> BTreeMap<String, BTreeMap<String, String>> {
+    let mut sections: BTreeMap<String, BTreeMap<String, String>> = BTreeMap::new();
+    let mut current = None;
+
+    for line in text.lines().map(str::trim) {
+        if let Some(header) = line.strip_prefix('#') {
+            let name = header.trim().to_string();
+            sections.entry(name.clone()).or_default();
+            current = Some(name);
+        } else if let Some((field, value)) = line.split_once(':')
+            && let Some(section) = current.as_ref().and_then(|name| sections.get_mut(name))
+        {
+            section.insert(field.to_string(), value.to_string());
+        }
+    }
+
+    sections
+}
+
+/// the sections as `# Section` headers followed by aligned `field  value` tables
+pub fn format_sections(sections: &BTreeMap<String, BTreeMap<String, String>>) -> String {
+    sections
+        .iter()
+        .map(|(name, fields)| {
+            let rows: Vec<(String, String)> = fields
+                .iter()
+                .map(|(field, value)| (field.clone(), display_value(field, value)))
+                .collect();
+            format!("# {}\n{}", name, output::format_table(&rows).trim_end())
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// byte counts like `used_memory` get their human size appended, `1048576 (1.00M)`
+fn display_value(field: &str, value: &str) -> String {
+    let is_bytes =
+        (field.contains("memory") || field.ends_with("_bytes")) && !field.ends_with("_human");
+    match value.parse::<u64>() {
+        Ok(bytes) if is_bytes && bytes >= 1024 => {
+            format!("{} ({})", value, output::format_bytes(bytes))
+        }
+        _ => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INFO: &str = "# Server\r\nredis_version:7.2.4\r\nuptime_in_seconds:1234\r\n\r\n\
+        # Memory\r\nused_memory:1572864\r\nused_memory_human:1.50M\r\nmaxmemory:0\r\n\r\n\
+        # Keyspace\r\ndb0:keys=3,expires=0,avg_ttl=0\r\n";
+
+    #[test]
+    fn info_is_split_into_sections() {
+        let sections = parse_info(INFO);
+        assert_eq!(
+            sections.keys().collect::<Vec<_>>(),
+            ["Keyspace", "Memory", "Server"]
+        );
+        assert_eq!(sections["Server"]["redis_version"], "7.2.4");
+        assert_eq!(sections["Memory"]["used_memory_human"], "1.50M");
+        // values keep their own colons and commas
+        assert_eq!(sections["Keyspace"]["db0"], "keys=3,expires=0,avg_ttl=0");
+    }
+
+    #[test]
+    fn lines_before_the_first_header_are_ignored() {
+        let sections = parse_info("stray:1\r\n# Empty\r\n");
+        assert_eq!(sections.len(), 1);
+        assert!(sections["Empty"].is_empty());
+    }
+
+    #[test]
+    fn byte_counts_get_a_human_size() {
+        assert_eq!(display_value("used_memory", "1572864"), "1572864 (1.50M)");
+        assert_eq!(display_value("used_memory", "1023"), "1023");
+        assert_eq!(display_value("used_memory_human", "1.50M"), "1.50M");
+        assert_eq!(display_value("uptime_in_seconds", "1234"), "1234");
+
+        let formatted = format_sections(&parse_info(INFO));
+        assert!(formatted.starts_with("# Keyspace\n"));
+        assert!(formatted.contains("used_memory"));
+        assert!(formatted.contains("1572864 (1.50M)"));
+    }
+}
@@ -0,0 +1,95 @@
+use std::{
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+/// a streaming command like `SUBSCRIBE` is running and wants Ctrl-C for itself
+static STREAMING: AtomicBool = AtomicBool::new(false);
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// `--confirm-exit`: Ctrl-C outside a stream only exits when pressed twice
+static CONFIRM_EXIT: AtomicBool = AtomicBool::new(false);
+/// when Ctrl-C was last pressed outside a stream without exiting
+static LAST_EXIT_REQUEST: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// with `--confirm-exit`, a second Ctrl-C within this long exits
+const CONFIRM_EXIT_WINDOW: Duration = Duration::from_secs(2);
+
+/// exit status of a process killed by SIGINT
+const EXIT_INTERRUPTED: i32 = 130;
+
+/// route Ctrl-C to a running stream; outside one it runs `on_exit`, like saving the
+/// history, and exits like the default handler, with `confirm_exit` only on a second press
+pub fn install(confirm_exit: bool, on_exit: impl Fn() + Send + 'static) -> anyhow::Result<()> {
+    CONFIRM_EXIT.store(confirm_exit, Ordering::SeqCst);
+    // the handler runs on a thread of its own while the main one may be blocked reading
+    // a reply or a line, so it applies the exit policy itself
+    ctrlc::set_handler(move || {
+        if STREAMING.load(Ordering::SeqCst) {
+            INTERRUPTED.store(true, Ordering::SeqCst);
+        } else if exit_confirmed() {
+            on_exit();
+            std::process::exit(EXIT_INTERRUPTED);
+        } else {
+            eprintln!("\npress Ctrl-C again to exit");
+        }
+    })?;
+    Ok(())
+}
+
+/// a Ctrl-C outside a stream, `true` when it should exit; with `--confirm-exit` that
+/// takes a second press within `CONFIRM_EXIT_WINDOW`
+pub fn exit_confirmed() -> bool {
+    if !CONFIRM_EXIT.load(Ordering::SeqCst) {
+        return true;
+    }
+    let mut last = LAST_EXIT_REQUEST.lock().unwrap();
+    if last.is_some_and(|at| at.elapsed() < CONFIRM_EXIT_WINDOW) {
+        return true;
+    }
+    *last = Some(Instant::now());
+    false
+}
+
+/// Ctrl-C stops the stream instead of the process while this is alive
+pub struct Streaming;
+
+impl Streaming {
+    pub fn start() -> Streaming {
+        INTERRUPTED.store(false, Ordering::SeqCst);
+        STREAMING.store(true, Ordering::SeqCst);
+        Streaming
+    }
+
+    pub fn interrupted(&self) -> bool {
+        INTERRUPTED.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for Streaming {
+    fn drop(&mut self) {
+        STREAMING.store(false, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn confirm_exit_takes_a_second_press() {
+        assert!(exit_confirmed());
+
+        CONFIRM_EXIT.store(true, Ordering::SeqCst);
+        assert!(!exit_confirmed());
+        assert!(exit_confirmed());
+
+        // a press outside the window starts over
+        *LAST_EXIT_REQUEST.lock().unwrap() = Instant::now().checked_sub(CONFIRM_EXIT_WINDOW);
+        assert!(!exit_confirmed());
+        CONFIRM_EXIT.store(false, Ordering::SeqCst);
+    }
+}
@@ -0,0 +1,73 @@
+use anyhow::{anyhow, bail};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde_json::Value;
+
+/// parse a JSON array of command arguments into exact bytes,
+/// each element is a string or `{"b64": "..."}` for binary data,
+/// like `["SET", "key", {"b64": "AP8K"}]`
+pub fn parse_json_args(text: &str) -> anyhow::Result<Vec<Vec<u8>>> {
+    let value: Value =
+        serde_json::from_str(text).map_err(|e| anyhow!("invalid JSON arguments: {}", e))?;
+    let Value::Array(elements) = value else {
+        bail!("JSON arguments must be an array");
+    };
+    if elements.is_empty() {
+        bail!("JSON arguments must not be empty");
+    }
+
+    elements
+        .iter()
+        .enumerate()
+        .map(|(i, element)| match element {
+            Value::String(s) => Ok(s.as_bytes().to_vec()),
+            Value::Object(object) if object.len() == 1 => match object.get("b64") {
+                Some(Value::String(encoded)) => STANDARD
+                    .decode(encoded)
+                    .map_err(|e| anyhow!("argument {}: invalid base64: {}", i, e)),
+                _ => bail!("argument {}: expected {{\"b64\": \"...\"}}", i),
+            },
+            _ => bail!("argument {}: expected a string or {{\"b64\": \"...\"}}", i),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_elements_decode_to_exact_bytes() {
+        let args = parse_json_args(r#"["SET", "key", {"b64": "AP8K"}]"#).unwrap();
+        assert_eq!(
+            args,
+            vec![b"SET".to_vec(), b"key".to_vec(), vec![0x00, 0xff, 0x0a]]
+        );
+    }
+
+    #[test]
+    fn strings_keep_spaces_and_utf8() {
+        let args = parse_json_args(r#"["SET", "a key", "héllo"]"#).unwrap();
+        assert_eq!(args[1], b"a key");
+        assert_eq!(args[2], "héllo".as_bytes());
+    }
+
+    #[test]
+    fn malformed_arguments_are_errors() {
+        let error = |text| parse_json_args(text).unwrap_err().to_string();
+        assert!(error("[\"GET\"").starts_with("invalid JSON arguments"));
+        assert_eq!(
+            error(r#"{"b64": "AA=="}"#),
+            "JSON arguments must be an array"
+        );
+        assert_eq!(error("[]"), "JSON arguments must not be empty");
+        assert!(error(r#"["SET", {"b64": "!!"}]"#).starts_with("argument 1: invalid base64"));
+        assert_eq!(
+            error(r#"["SET", {"hex": "00"}]"#),
+            r#"argument 1: expected {"b64": "..."}"#
+        );
+        assert_eq!(
+            error(r#"["INCRBY", "k", 2]"#),
+            r#"argument 2: expected a string or {"b64": "..."}"#
+        );
+    }
+}
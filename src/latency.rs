@@ -0,0 +1,52 @@
+use std::{fmt, time::Duration};
+
+/// round-trip times sampled by `--latency`, in milliseconds like redis-cli
+#[derive(Debug, Default)]
+pub struct LatencyStats {
+    min: f64,
+    max: f64,
+    /// running mean, so samples aren't kept
+    avg: f64,
+    samples: u64,
+}
+
+impl LatencyStats {
+    pub fn add(&mut self, rtt: Duration) {
+        let ms = rtt.as_secs_f64() * 1000.0;
+        if self.samples == 0 || ms < self.min {
+            self.min = ms;
+        }
+        if ms > self.max {
+            self.max = ms;
+        }
+        self.samples += 1;
+        self.avg += (ms - self.avg) / self.samples as f64;
+    }
+}
+
+impl fmt::Display for LatencyStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "min: {:.2}, max: {:.2}, avg: {:.2} ({} samples)",
+            self.min, self.max, self.avg, self.samples
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn min_max_and_running_average() {
+        let mut stats = LatencyStats::default();
+        for ms in [3, 1, 2, 6] {
+            stats.add(Duration::from_millis(ms));
+        }
+        assert_eq!(
+            stats.to_string(),
+            "min: 1.00, max: 6.00, avg: 3.00 (4 samples)"
+        );
+    }
+}
@@ -0,0 +1,92 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::{redis_client::RedisClient, redis_type::RespType};
+
+/// most fields or members fetched for one key
+const FETCH_CAP: usize = 100;
+
+/// fetched fields and members are reused for this long
+const CACHE_TTL: Duration = Duration::from_secs(5);
+
+/// `(kind, key)` to the values fetched and when
+type FetchedValues = HashMap<(&'static str, String), (Instant, Vec<String>)>;
+
+/// `--live-completion`: complete hash fields and set or sorted set members from the
+/// key being typed, on a connection of its own to the database given at startup
+pub struct LiveCompletion {
+    client: Mutex<RedisClient>,
+    cached: Mutex<FetchedValues>,
+}
+
+impl LiveCompletion {
+    pub fn new(client: RedisClient) -> Self {
+        Self {
+            client: Mutex::new(client),
+            cached: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// fields or members for the argument at `current_pos`, empty when it is neither
+    pub fn complete(&self, command: &str, args: &[String], current_pos: usize) -> Vec<String> {
+        let kind = match (command, current_pos) {
+            ("HGET" | "HDEL" | "HEXISTS" | "HSTRLEN" | "HSET" | "HINCRBY", 1) => "hash",
+            ("ZADD", 2) | ("ZREM" | "ZSCORE" | "ZRANK" | "ZINCRBY", 1) => "zset",
+            ("SREM" | "SISMEMBER", 1) => "set",
+            _ => return Vec::new(),
+        };
+        let Some(key) = args.first() else {
+            return Vec::new();
+        };
+
+        let mut cached = self.cached.lock().unwrap();
+        let cache_key = (kind, key.clone());
+        if let Some((fetched_at, values)) = cached.get(&cache_key)
+            && fetched_at.elapsed() < CACHE_TTL
+        {
+            return values.clone();
+        }
+
+        let values = self.fetch(kind, key);
+        cached.insert(cache_key, (Instant::now(), values.clone()));
+        values
+    }
+
+    /// one round trip, capped by `FETCH_CAP`; errors like a wrong type complete nothing
+    fn fetch(&self, kind: &str, key: &str) -> Vec<String> {
+        let key = key.as_bytes();
+        let cap = FETCH_CAP.to_string();
+        let last = (FETCH_CAP - 1).to_string();
+        let args: Vec<&[u8]> = match kind {
+            "hash" => vec![b"HSCAN", key, b"0", b"COUNT", cap.as_bytes()],
+            "set" => vec![b"SSCAN", key, b"0", b"COUNT", cap.as_bytes()],
+            _ => vec![b"ZRANGE", key, b"0", last.as_bytes()],
+        };
+
+        let reply = match self.client.lock().unwrap().execute(&args) {
+            Ok(reply) => reply,
+            Err(_) => return Vec::new(),
+        };
+        // `[cursor, items]` for the SCAN variants, HSCAN items alternate field and value
+        let items = match reply {
+            RespType::Arrays(array) if kind != "zset" && array.len() == 2 => {
+                match array.into_vec().pop() {
+                    Some(RespType::Arrays(items)) => items.into_vec(),
+                    _ => return Vec::new(),
+                }
+            }
+            RespType::Arrays(array) => array.into_vec(),
+            _ => return Vec::new(),
+        };
+        let step = if kind == "hash" { 2 } else { 1 };
+        items
+            .iter()
+            .step_by(step)
+            .take(FETCH_CAP)
+            .map(|item| item.to_string())
+            .collect()
+    }
+}
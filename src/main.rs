@@ -1,125 +1,322 @@
 use std::env::{self};
+use std::io::{self, IsTerminal};
+use std::path::PathBuf;
+use std::process;
 use std::result::Result::Ok;
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
 
 use anyhow::Result as AnyhowResult;
-use rustyline::Editor;
+use rustyline::{Config, Editor, EventHandler, KeyEvent};
 
 use crate::{
+    cli::{CliArgs, USAGE},
     command_cache::CommandCache,
+    history::{History, HistorySearch},
+    json_args::parse_json_args,
+    live_completion::LiveCompletion,
+    output::OutputOptions,
     redis_client::{RedisAddress, RedisClient},
-    redis_type::Hello,
+    redis_type::{Hello, RespType},
+    session::{EXIT_FAILURE, LineAction, Safety, Session},
     smart_completer::SmartCompleter,
+    transcript::Transcript,
 };
 
+mod bigkeys;
+mod browse;
 mod byte_buffer;
+mod cli;
+mod clients;
+mod cluster;
+mod color;
 mod command_cache;
+mod help;
+mod history;
+mod info;
+mod interrupt;
+mod json_args;
+mod latency;
+mod live_completion;
+mod memory;
+mod output;
 mod redis_client;
 mod redis_type;
+mod session;
+mod slowlog;
 mod smart_completer;
+mod tls;
+mod tokenizer;
+mod transcript;
+
+/// lines kept in the history file
+const HISTORY_SIZE: usize = 1000;
 
 fn main() -> AnyhowResult<()> {
     // parse command line arguments
-    let args: Vec<String> = env::args().collect();
-
-    let redis_address = if args.len() == 2 {
-        RedisAddress::new(&args[1], 6379, Hello::no_auth())
-    } else if args.len() == 3 {
-        RedisAddress::new(&args[1], args[2].parse()?, Hello::no_auth())
-    } else if args.len() == 4 {
-        RedisAddress::new(
-            &args[1],
-            args[2].parse()?,
-            Hello::with_password("default", &args[3]),
-        )
-    } else {
-        println!("./rredis-cli.exe usage: ./rredis-cli.exe host [port [password]]");
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args
+        .iter()
+        .take_while(|arg| *arg != "--")
+        .any(|arg| arg == "--version")
+    {
+        println!("rredis-cli {}", env!("CARGO_PKG_VERSION"));
         return Ok(());
+    }
+    let Some(cli_args) = CliArgs::parse(&args)? else {
+        println!("{}", USAGE);
+        return Ok(());
+    };
+
+    let hello = match &cli_args.resolve_password()? {
+        Some(password) => Hello::with_password("default", password),
+        None => Hello::no_auth(),
+    };
+    let redis_address =
+        if cli_args.host.starts_with("redis://") || cli_args.host.starts_with("rediss://") {
+            RedisAddress::from_uri(&cli_args.host)?.with_hello_fallback(hello)
+        } else {
+            RedisAddress::new(&cli_args.host, cli_args.port, hello)
+        }
+        .with_db(cli_args.db)
+        .with_proto(cli_args.proto)
+        .with_debug(cli_args.debug)
+        .with_tls(cli_args.tls.clone())
+        .with_timeout(cli_args.timeout);
+
+    let transcript = match &cli_args.transcript {
+        Some(path) => Some(Transcript::open(path)?),
+        None => None,
+    };
+
+    // exact arguments from JSON (`-` reads them from stdin) or an --eval script
+    let exact_args = match exact_args(&cli_args) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            process::exit(EXIT_FAILURE);
+        }
     };
+    let one_shot = cli_args.command.is_some() || exact_args.is_some();
 
     // create client
-    let mut redis_client = RedisClient::connect(redis_address)?;
+    let redis_client = match RedisClient::connect(redis_address.clone()) {
+        Ok(client) => client,
+        Err(e) if one_shot => {
+            eprintln!("Error: {}", e);
+            process::exit(EXIT_FAILURE);
+        }
+        Err(e) => return Err(e),
+    };
 
     // 创建命令缓存
-    let command_cache = Arc::new(Mutex::new(CommandCache::new()));
+    let mut cache = CommandCache::new();
+    if let Some(key_cap) = cli_args.key_cap {
+        cache.set_key_cap(key_cap);
+    }
+    let command_cache = Arc::new(Mutex::new(cache));
+    let history = Arc::new(Mutex::new(open_history()));
+    let output_options = OutputOptions {
+        group_digits: cli_args.group_digits,
+        with_ttl: cli_args.with_ttl,
+        timing: cli_args.time,
+        verbose: cli_args.verbose,
+        max_elements: cli_args.max_elements,
+        render: cli_args.render,
+        raw: cli_args.raw.unwrap_or(!io::stdout().is_terminal()),
+    };
+    let mut session = Session::new(
+        redis_client,
+        command_cache.clone(),
+        transcript,
+        output_options,
+        Safety::new(cli_args.test_mode),
+        history.clone(),
+    )
+    .with_cluster(cli_args.cluster)
+    .with_arity_check(!cli_args.no_arity_check);
+
+    let exit_history = history.clone();
+    interrupt::install(cli_args.confirm_exit, move || {
+        save_history(&exit_history.lock().unwrap())
+    })?;
+
+    // run a single command, `-r` times, and exit
+    if let Some(args) = &exact_args {
+        process::exit(session.run_repeated(
+            cli_args.repeat,
+            cli_args.interval,
+            &mut io::stdout(),
+            |session, out| session.run_args(args, out),
+        ));
+    }
+    if let Some(command) = &cli_args.command {
+        process::exit(session.run_repeated(
+            cli_args.repeat,
+            cli_args.interval,
+            &mut io::stdout(),
+            |session, out| session.run_command(command, out),
+        ));
+    }
+
+    if cli_args.bigkeys {
+        process::exit(session.run_bigkeys(&mut io::stdout()));
+    }
+    if cli_args.latency {
+        process::exit(session.run_latency(&mut io::stdout()));
+    }
+
+    // piped input is sent in pipelined batches
+    if !cli_args.simple && !io::stdin().is_terminal() {
+        let code = if cli_args.multi {
+            session.run_transaction(&mut io::stdin().lock(), &mut io::stdout())
+        } else {
+            session.run_pipe(&mut io::stdin().lock(), &mut io::stdout())
+        };
+        process::exit(code);
+    }
+
+    if cli_args.test_mode {
+        print_test_mode_banner();
+    }
+
+    println!(
+        "Connected successfully! (RESP{})",
+        session.client().proto().str_ver()
+    );
+    // no HELLO reply from servers before 6.0
+    if !matches!(session.client().hello_reply(), RespType::Unknown) {
+        println!("{}", session.client().hello_reply());
+    }
+    if cli_args.show_db_info
+        && let Some(summary) = session.db_summary()
+    {
+        println!("{}", summary);
+    }
+
+    if cli_args.simple {
+        let result = session.run_simple(&mut io::stdin().lock(), &mut io::stdout());
+        save_history(&history.lock().unwrap());
+        return Ok(result?);
+    }
 
     // 启动后台线程来获取命令信息和更新keys
     let cache_clone = command_cache.clone();
-    let host = args[1].clone();
-    let port = if args.len() >= 3 {
-        args[2].parse::<u16>().unwrap_or(6379)
-    } else {
-        6379
-    };
+    // 后台连接不输出 --debug 跟踪，只跟踪用户输入的命令
+    let cache_address = redis_address.clone().with_debug(false);
 
     thread::spawn(move || {
-        let mut client =
-            match RedisClient::connect(RedisAddress::new(&host, port, Hello::no_auth())) {
-                Ok(c) => c,
-                Err(_) => {
-                    eprintln!("Warning: Could not connect to Redis for command cache");
-                    return;
+        let mut client: Option<RedisClient> = None;
+        let mut warned = false;
+
+        loop {
+            // 连接断开后重连，服务器版本变化时清空缓存
+            if client.is_none() {
+                match RedisClient::connect(cache_address.clone()) {
+                    Ok(c) => client = Some(c),
+                    Err(_) if !warned => {
+                        eprintln!("Warning: Could not connect to Redis for command cache");
+                        warned = true;
+                    }
+                    Err(_) => {}
                 }
-            };
 
-        // 获取命令文档
-        if let Err(e) = cache_clone.lock().unwrap().fetch_command_docs(&mut client) {
-            eprintln!("Warning: Could not fetch command docs: {}", e);
-        }
+                if let Some(c) = client.as_mut() {
+                    let needs_docs = {
+                        let mut cache = cache_clone.lock().unwrap();
+                        cache.track_server_version(&c.server_info().version);
+                        cache.set_databases(c.databases());
+                        !cache.has_command_docs()
+                    };
 
-        loop {
-            // 更新keys缓存
-            let _ = cache_clone.lock().unwrap().update_keys(&mut client);
+                    // 获取命令文档，网络往返期间不持有锁，补全和提示不会被阻塞
+                    if needs_docs {
+                        match CommandCache::fetch_command_docs(c) {
+                            Ok(commands) => cache_clone.lock().unwrap().set_commands(commands),
+                            Err(e) => eprintln!("Warning: Could not fetch command docs: {}", e),
+                        }
+                    }
+                }
+            }
+
+            // 更新keys缓存，同样只在替换结果时加锁
+            if let Some(c) = client.as_mut() {
+                let (stale, key_cap) = {
+                    let cache = cache_clone.lock().unwrap();
+                    (cache.keys_stale(), cache.key_cap())
+                };
+                if stale {
+                    match CommandCache::fetch_keys(c, key_cap) {
+                        Ok(Some(keys)) => cache_clone.lock().unwrap().set_keys(keys),
+                        Ok(None) => {}
+                        Err(_) => client = None,
+                    }
+                }
+            }
 
             thread::sleep(Duration::from_secs(30));
         }
     });
 
     // 创建智能补全器
-    let completer = SmartCompleter::new(command_cache.clone());
-    let mut editor = Editor::<SmartCompleter, rustyline::history::DefaultHistory>::new()?;
+    let live_completion = if cli_args.live_completion {
+        match RedisClient::connect(redis_address.clone().with_debug(false)) {
+            Ok(client) => Some(LiveCompletion::new(client)),
+            Err(e) => {
+                eprintln!("Warning: live completion disabled: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    let completer =
+        SmartCompleter::new(command_cache.clone()).with_live_completion(live_completion);
+    let config = Config::builder().max_history_size(HISTORY_SIZE)?.build();
+    let mut editor =
+        Editor::<SmartCompleter, rustyline::history::DefaultHistory>::with_config(config)?;
     editor.set_helper(Some(completer));
+    // Ctrl-R 在会话历史中查找包含已输入内容的命令
+    editor.bind_sequence(
+        KeyEvent::ctrl('R'),
+        EventHandler::Conditional(Box::new(HistorySearch::new(history.clone()))),
+    );
+
+    // 方向键浏览的历史记录来自会话历史
+    for entry in history.lock().unwrap().entries() {
+        editor.add_history_entry(entry)?;
+    }
 
     println!("Redis CLI with smart completion");
     println!("Type 'help' for available commands or 'quit' to exit");
-    println!("Press Tab for command completion");
+    println!("Press Tab for command completion, Ctrl-R to search history");
 
     // loop for user input
+    let mut stdout = io::stdout();
     loop {
-        match editor.readline("> ") {
+        let prompt = session.client().prompt();
+        match editor.readline(&prompt) {
             Ok(line) => {
                 let command: &str = line.trim();
-                if command.is_empty() {
-                    continue;
-                }
-
-                if command == "quit" || command == "exit" {
-                    break;
-                }
-
-                if command == "help" {
-                    print_help();
+                if !command.is_empty() && !history::is_sensitive(command) {
+                    // 添加到历史记录，带密码的命令除外
                     editor.add_history_entry(command.to_string())?;
-                    continue;
                 }
 
-                // 添加到历史记录
-                editor.add_history_entry(command.to_string())?;
-
-                // 执行命令
-                match redis_client.execute_command(command) {
-                    Ok(response) => {
-                        println!("{}", response);
-                    }
-                    Err(e) => {
-                        eprintln!("Error: {}", e);
-                    }
+                let mut stdin = io::stdin().lock();
+                if session.execute_line(&prompt, &line, &mut stdin, &mut stdout)?
+                    == LineAction::Quit
+                {
+                    break;
                 }
             }
             Err(rustyline::error::ReadlineError::Interrupted) => {
                 println!("^C");
+                if !interrupt::exit_confirmed() {
+                    println!("press Ctrl-C again to exit");
+                    continue;
+                }
                 break;
             }
             Err(rustyline::error::ReadlineError::Eof) => {
@@ -133,54 +330,70 @@ fn main() -> AnyhowResult<()> {
         }
     }
 
+    save_history(&history.lock().unwrap());
+
     Ok(())
 }
 
-fn print_help() {
-    println!("Available commands:");
-    println!("  GET <key>           - Get value of key");
-    println!("  SET <key> <value>   - Set value of key");
-    println!("  DEL <key>           - Delete key");
-    println!("  KEYS <pattern>      - Find all keys matching pattern");
-    println!("  EXISTS <key>        - Check if key exists");
-    println!("  TYPE <key>          - Get type of key");
-    println!("  TTL <key>           - Get time to live of key");
-    println!("  EXPIRE <key> <seconds> - Set expiration on key");
-    println!("  INFO [section]      - Get information and statistics about server");
-    println!("  CONFIG GET <parameter> - Get configuration parameter");
-    println!("  CONFIG SET <parameter> <value> - Set configuration parameter");
-    println!("  PING                - Ping server");
-    println!("  FLUSHDB             - Remove all keys from current database");
-    println!("  FLUSHALL            - Remove all keys from all databases");
-    println!("");
-    println!("Hash commands:");
-    println!("  HGET <key> <field>  - Get value of field in hash");
-    println!("  HSET <key> <field> <value> - Set field in hash");
-    println!("  HDEL <key> <field>  - Delete field from hash");
-    println!("  HGETALL <key>       - Get all fields and values in hash");
-    println!("");
-    println!("List commands:");
-    println!("  LPUSH <key> <value> - Prepend value to list");
-    println!("  RPUSH <key> <value> - Append value to list");
-    println!("  LPOP <key>          - Remove and get first element");
-    println!("  RPOP <key>          - Remove and get last element");
-    println!("  LLEN <key>          - Get length of list");
-    println!("");
-    println!("Set commands:");
-    println!("  SADD <key> <member> - Add member to set");
-    println!("  SREM <key> <member> - Remove member from set");
-    println!("  SMEMBERS <key>      - Get all members in set");
-    println!("  SCARD <key>         - Get number of members in set");
-    println!("");
-    println!("Sorted Set commands:");
-    println!("  ZADD <key> <score> <member> - Add member to sorted set");
-    println!("  ZREM <key> <member> - Remove member from sorted set");
-    println!("  ZRANGE <key> <start> <stop> - Get range of members");
-    println!("  ZCARD <key>         - Get number of members in sorted set");
-    println!("");
-    println!("Features:");
-    println!("  - Tab completion for commands and keys");
-    println!("  - Smart parameter suggestions");
-    println!("  - Command history (use arrow keys)");
-    println!("  - Context-aware completion");
+/// the history file's entries, or an in-memory history when there's no usable file
+fn open_history() -> History {
+    let Some(path) = history_file() else {
+        return History::new(HISTORY_SIZE);
+    };
+    History::open(&path, HISTORY_SIZE).unwrap_or_else(|e| {
+        eprintln!(
+            "Warning: Could not load history from {}: {}",
+            path.display(),
+            e
+        );
+        History::new(HISTORY_SIZE)
+    })
+}
+
+fn save_history(history: &History) {
+    if let Err(e) = history.save()
+        && let Some(path) = history.path()
+    {
+        eprintln!(
+            "Warning: Could not save history to {}: {}",
+            path.display(),
+            e
+        );
+    }
+}
+
+/// `$RREDIS_HISTFILE`, or `.rredis_cli_history` in the home directory
+fn history_file() -> Option<PathBuf> {
+    if let Some(path) = env::var_os("RREDIS_HISTFILE").filter(|path| !path.is_empty()) {
+        return Some(PathBuf::from(path));
+    }
+
+    env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .map(|home| PathBuf::from(home).join(".rredis_cli_history"))
+}
+
+/// a command given as exact argument bytes, from `--args-json` or `--eval`; `None` for
+/// a plain command line
+fn exact_args(cli_args: &CliArgs) -> anyhow::Result<Option<Vec<Vec<u8>>>> {
+    if let Some(text) = &cli_args.args_json {
+        let text = if text == "-" {
+            io::read_to_string(io::stdin())?
+        } else {
+            text.clone()
+        };
+        return Ok(Some(parse_json_args(&text)?));
+    }
+
+    cli_args.eval_command()
+}
+
+/// make it obvious that safety checks are off, so it's never mistaken for production
+fn print_test_mode_banner() {
+    let line = "*".repeat(64);
+    let text = format!(
+        "{}\n*  TEST MODE: flush confirmations and DEBUG warnings are OFF  *\n{}",
+        line, line
+    );
+    println!("{}", color::paint(&text, color::BOLD_RED));
 }
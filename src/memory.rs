@@ -0,0 +1,71 @@
+use crate::{output, redis_type::RespType};
+
+/// flatten a `MEMORY STATS` reply into `(dotted.key, value)` rows, nested maps like `db.0`
+/// become `db.0.overhead.hashtable.main`, byte counts are converted to human sizes
+pub fn stats_rows(stats: &RespType) -> Vec<(String, String)> {
+    let mut rows = vec![];
+    flatten(stats, "", &mut rows);
+    rows
+}
+
+fn flatten(resp: &RespType, prefix: &str, rows: &mut Vec<(String, String)>) {
+    let entries: Vec<(&RespType, &RespType)> = match resp {
+        RespType::Maps(map) => map.iter().collect(),
+        // RESP2 returns a flat key-value array
+        RespType::Arrays(arr) => arr
+            .as_slice()
+            .chunks_exact(2)
+            .map(|pair| (&pair[0], &pair[1]))
+            .collect(),
+        _ => return,
+    };
+
+    for (key, value) in entries {
+        let key = if prefix.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+
+        match value {
+            RespType::Maps(_) | RespType::Arrays(_) => flatten(value, &key, rows),
+            RespType::Integers(i) if is_byte_field(&key) && i.value() >= 0 => {
+                rows.push((key, output::format_bytes(i.value() as u64)))
+            }
+            _ => rows.push((key, value.to_string())),
+        }
+    }
+}
+
+/// integer stats are byte sizes except counters and percentages
+fn is_byte_field(key: &str) -> bool {
+    !key.ends_with(".count") && !key.contains("percentage")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::byte_buffer::BytesBuffer;
+
+    #[test]
+    fn memory_stats_are_flattened_with_human_sizes() {
+        let mut buff = BytesBuffer::new(64);
+        buff.put_u8_slice(
+            b"%4\r\n$14\r\npeak.allocated\r\n:1073741824\r\n$4\r\ndb.0\r\n%1\r\n$23\r\noverhead.hashtable.main\r\n:1536\r\n$10\r\nkeys.count\r\n:12345\r\n$18\r\ndataset.percentage\r\n,12.5\r\n",
+        );
+        let rows = stats_rows(&RespType::decode(&mut buff));
+        let rows: Vec<(&str, &str)> = rows
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                ("peak.allocated", "1.00G"),
+                ("db.0.overhead.hashtable.main", "1.50K"),
+                ("keys.count", "12345"),
+                ("dataset.percentage", "12.5"),
+            ]
+        );
+    }
+}
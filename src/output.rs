@@ -0,0 +1,622 @@
+use std::time::Duration;
+
+use crate::{color, redis_type::RespType};
+
+/// how replies are rendered for display
+#[derive(Debug, Clone, Default)]
+pub struct OutputOptions {
+    /// render integers with thousands separators, like `1,234,567`
+    pub group_digits: bool,
+    /// append the key's TTL after single-key reads
+    pub with_ttl: bool,
+    /// show how long each command's round trip took
+    pub timing: bool,
+    /// show each reply's size in bytes and top-level elements
+    pub verbose: bool,
+    /// show at most this many top-level elements of arrays, sets and maps
+    pub max_elements: Option<usize>,
+    /// force one layout for every reply instead of the per command profile
+    pub render: Option<RenderHint>,
+    /// plain values one per line for scripts, instead of redis-cli style numbered elements
+    pub raw: bool,
+}
+
+/// command specific layout for replies the generic renderer garbles
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderHint {
+    Generic,
+    /// field/value pairs as an aligned two column table
+    Table,
+    /// a list of stream entries, or ids with `JUSTID`
+    Stream,
+    /// `XAUTOCLAIM`: next cursor, claimed entries and deleted ids
+    XAutoClaim,
+    /// `GEOPOS`: longitude/latitude pairs
+    Geo,
+}
+
+/// the layout used for each command, two word entries match a subcommand
+const PROFILES: &[(&str, RenderHint)] = &[
+    ("CONFIG GET", RenderHint::Table),
+    ("HGETALL", RenderHint::Table),
+    ("XRANGE", RenderHint::Stream),
+    ("XREVRANGE", RenderHint::Stream),
+    ("XCLAIM", RenderHint::Stream),
+    ("XAUTOCLAIM", RenderHint::XAutoClaim),
+    ("GEOPOS", RenderHint::Geo),
+];
+
+impl RenderHint {
+    /// look up the layout profile for a command line, like `CONFIG GET *`
+    pub fn for_command(command_line: &str) -> RenderHint {
+        let words: Vec<String> = command_line
+            .split_whitespace()
+            .take(2)
+            .map(str::to_uppercase)
+            .collect();
+        let Some(command) = words.first() else {
+            return RenderHint::Generic;
+        };
+        let subcommand = words.get(1).map(|sub| format!("{} {}", command, sub));
+
+        PROFILES
+            .iter()
+            .find(|(name, _)| Some(*name) == subcommand.as_deref())
+            .or_else(|| PROFILES.iter().find(|(name, _)| name == command))
+            .map_or(RenderHint::Generic, |(_, hint)| *hint)
+    }
+
+    /// the layout forced by `--render`, falling back to the command's profile;
+    /// raw output skips the profiles so scripts get one plain value per line
+    pub fn resolve(command_line: &str, options: &OutputOptions) -> RenderHint {
+        options.render.unwrap_or_else(|| {
+            if options.raw {
+                RenderHint::Generic
+            } else {
+                RenderHint::for_command(command_line)
+            }
+        })
+    }
+
+    pub fn from_name(name: &str) -> Option<RenderHint> {
+        match name.to_lowercase().as_str() {
+            "generic" => Some(RenderHint::Generic),
+            "table" => Some(RenderHint::Table),
+            "stream" => Some(RenderHint::Stream),
+            "xautoclaim" => Some(RenderHint::XAutoClaim),
+            "geo" => Some(RenderHint::Geo),
+            _ => None,
+        }
+    }
+}
+
+/// render a reply using a command specific layout, falling back to `render`
+pub fn render_with_hint(resp: &RespType, hint: RenderHint, options: &OutputOptions) -> String {
+    match (hint, resp) {
+        (RenderHint::Table, RespType::Maps(map)) if !map.is_empty() => format_table(
+            &map.iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect::<Vec<_>>(),
+        ),
+        (RenderHint::Table, RespType::Arrays(arr))
+            if !arr.is_empty() && arr.len().is_multiple_of(2) =>
+        {
+            format_table(
+                &arr.as_slice()
+                    .chunks_exact(2)
+                    .map(|pair| (pair[0].to_string(), pair[1].to_string()))
+                    .collect::<Vec<_>>(),
+            )
+        }
+        (RenderHint::Stream, RespType::Arrays(entries)) if !entries.is_empty() => {
+            render_stream_entries(entries.as_slice())
+        }
+        (RenderHint::Geo, RespType::Arrays(positions)) if !positions.is_empty() => {
+            let mut text = String::new();
+            for (i, position) in positions.iter().enumerate() {
+                match position {
+                    RespType::Arrays(p) if p.len() == 2 => text.push_str(&format!(
+                        "{}) longitude: {}, latitude: {}\n",
+                        i + 1,
+                        p.as_slice()[0],
+                        p.as_slice()[1]
+                    )),
+                    other => text.push_str(&format!("{}) {}\n", i + 1, other)),
+                }
+            }
+            text
+        }
+        (RenderHint::XAutoClaim, RespType::Arrays(reply)) if reply.len() >= 2 => {
+            let mut text = format!("cursor: {}\n", reply.as_slice()[0]);
+            text.push_str("claimed:\n");
+            if let RespType::Arrays(entries) = &reply.as_slice()[1] {
+                text.push_str(&render_stream_entries(entries.as_slice()));
+            }
+            // deleted ids are only returned since redis 7.0
+            if let Some(RespType::Arrays(deleted)) = reply.get(2)
+                && !deleted.is_empty()
+            {
+                text.push_str("deleted:\n");
+                for id in deleted.as_slice() {
+                    text.push_str(&format!("   {}\n", id));
+                }
+            }
+            text
+        }
+        _ => render(resp, options),
+    }
+}
+
+/// stream entries as `1) <id>` followed by indented `field: value` lines
+fn render_stream_entries(entries: &[RespType]) -> String {
+    let mut text = String::new();
+    for (i, entry) in entries.iter().enumerate() {
+        match entry {
+            RespType::Arrays(e) if e.len() == 2 => {
+                text.push_str(&format!("{}) {}\n", i + 1, e.as_slice()[0]));
+                if let RespType::Arrays(fields) = &e.as_slice()[1] {
+                    for pair in fields.as_slice().chunks_exact(2) {
+                        text.push_str(&format!("   {}: {}\n", pair[0], pair[1]));
+                    }
+                }
+            }
+            RespType::Nulls(_) => text.push_str(&format!("{}) (deleted)\n", i + 1)),
+            // JUSTID form
+            id => text.push_str(&format!("{}) {}\n", i + 1, id)),
+        }
+    }
+    text
+}
+
+/// render a reply for display using the output options
+pub fn render(resp: &RespType, options: &OutputOptions) -> String {
+    render_value(resp, options, options.max_elements)
+}
+
+/// `limit` caps the number of elements shown, it's only applied to the top-level reply
+fn render_value(resp: &RespType, options: &OutputOptions, limit: Option<usize>) -> String {
+    if !options.raw {
+        return render_nested(resp, options, 0, limit);
+    }
+
+    let shown = limit.unwrap_or(usize::MAX);
+    let (elements, total): (Vec<String>, usize) = match resp {
+        RespType::Arrays(a) if !a.is_empty() => (
+            a.iter()
+                .take(shown)
+                .map(|e| render_value(e, options, None))
+                .collect(),
+            a.len(),
+        ),
+        RespType::Sets(s) if !s.is_empty() => (
+            s.iter()
+                .take(shown)
+                .map(|e| render_value(e, options, None))
+                .collect(),
+            s.len(),
+        ),
+        RespType::Maps(m) if !m.is_empty() => (
+            m.iter()
+                .take(shown)
+                .map(|(key, value)| format!("{}: {}", key, render_value(value, options, None)))
+                .collect(),
+            m.len(),
+        ),
+        _ => return resp.to_string(),
+    };
+
+    let mut text: String = elements.iter().map(|e| format!("{}\n", e)).collect();
+    if total > elements.len() {
+        text.push_str(&format!("... and {} more\n", total - elements.len()));
+    }
+    text
+}
+
+/// redis-cli style: numbered elements, nested ones indented under their parent's index,
+/// like `1) 1) "a"` followed by `   2) "b"`
+fn render_nested(
+    resp: &RespType,
+    options: &OutputOptions,
+    indent: usize,
+    limit: Option<usize>,
+) -> String {
+    let (entries, mark, empty): (Vec<(Option<&RespType>, &RespType)>, char, &str) = match resp {
+        RespType::Arrays(a) => (a.iter().map(|e| (None, e)).collect(), ')', "(empty array)"),
+        RespType::Pushes(p) => (p.iter().map(|e| (None, e)).collect(), ')', "(empty array)"),
+        RespType::Sets(s) => (s.iter().map(|e| (None, e)).collect(), '~', "(empty set)"),
+        RespType::Maps(m) => (
+            m.iter().map(|(key, value)| (Some(key), value)).collect(),
+            '#',
+            "(empty hash)",
+        ),
+        _ => return render_scalar(resp, options),
+    };
+    if entries.is_empty() {
+        return empty.to_string();
+    }
+
+    // indexes are right aligned, nested elements start after `<index>) `
+    let shown = limit.unwrap_or(usize::MAX);
+    let width = entries.len().min(shown).to_string().len();
+    let nested_indent = indent + width + 2;
+    let mut text = String::new();
+    for (i, (key, value)) in entries.iter().take(shown).enumerate() {
+        if i > 0 {
+            text.push('\n');
+            text.push_str(&" ".repeat(indent));
+        }
+        text.push_str(&format!("{:>width$}{} ", i + 1, mark, width = width));
+        if let Some(key) = key {
+            text.push_str(&render_nested(key, options, nested_indent, None));
+            text.push_str(" => ");
+        }
+        text.push_str(&render_nested(value, options, nested_indent, None));
+    }
+    if entries.len() > shown {
+        text.push_str(&format!(
+            "\n{}... and {} more",
+            " ".repeat(indent),
+            entries.len() - shown
+        ));
+    }
+    text
+}
+
+/// a single value the way redis-cli shows it, strings quoted and other types tagged,
+/// errors in red and integers in green when colors are enabled
+fn render_scalar(resp: &RespType, options: &OutputOptions) -> String {
+    match resp {
+        RespType::BulkStrings(bs) => quote(bs.value()),
+        RespType::Integers(i) if options.group_digits => color::paint(
+            &format!("(integer) {}", group_digits(i.value() as i64)),
+            color::GREEN,
+        ),
+        RespType::Integers(i) => color::paint(&format!("(integer) {}", i.value()), color::GREEN),
+        RespType::Doubles(d) => format!("(double) {}", d.digits()),
+        RespType::Booleans(_) => format!("({})", resp),
+        RespType::BigNumbers(_) => format!("(big number) {}", resp),
+        // plain text as is, other formats like markdown are labelled
+        RespType::VerbatimStrings(vs) if vs.format() != "txt" => {
+            format!("({}) {}", vs.format(), vs.value())
+        }
+        RespType::Nulls(_) => "(nil)".to_string(),
+        RespType::SimpleErrors(_) | RespType::BulkErrors(_) => {
+            color::paint(&format!("(error) {}", resp), color::RED)
+        }
+        _ => resp.to_string(),
+    }
+}
+
+/// double quoted with escapes, non printable bytes as `\xHH` like redis-cli
+pub fn quote(bytes: &[u8]) -> String {
+    let mut quoted = String::with_capacity(bytes.len() + 2);
+    quoted.push('"');
+    for &byte in bytes {
+        match byte {
+            b'"' => quoted.push_str("\\\""),
+            b'\\' => quoted.push_str("\\\\"),
+            b'\n' => quoted.push_str("\\n"),
+            b'\r' => quoted.push_str("\\r"),
+            b'\t' => quoted.push_str("\\t"),
+            0x07 => quoted.push_str("\\a"),
+            0x08 => quoted.push_str("\\b"),
+            b' '..=b'~' => quoted.push(byte as char),
+            _ => quoted.push_str(&format!("\\x{:02x}", byte)),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// rows as two aligned columns
+pub fn format_table(rows: &[(String, String)]) -> String {
+    let width = rows.iter().map(|(key, _)| key.len()).max().unwrap_or(0);
+    rows.iter()
+        .map(|(key, value)| format!("{:<width$}  {}\n", key, value, width = width))
+        .collect()
+}
+
+/// rows, the first one a header, as aligned columns; columns in `right_aligned`
+/// are padded on the left like numbers and the last column isn't padded
+pub fn format_columns(rows: &[Vec<String>], right_aligned: &[usize]) -> String {
+    let mut widths: Vec<usize> = vec![];
+    for row in rows {
+        widths.resize(widths.len().max(row.len()), 0);
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    rows.iter()
+        .map(|row| {
+            row.iter()
+                .enumerate()
+                .map(|(i, cell)| match i {
+                    _ if i + 1 == row.len() => cell.clone(),
+                    _ if right_aligned.contains(&i) => format!("{:>1$}", cell, widths[i]),
+                    _ => format!("{:<1$}", cell, widths[i]),
+                })
+                .collect::<Vec<_>>()
+                .join("  ")
+                .trim_end()
+                .to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// a round trip like `(1.23 ms)`
+pub fn format_elapsed(elapsed: Duration) -> String {
+    format!("({:.2} ms)", elapsed.as_secs_f64() * 1000.0)
+}
+
+/// a reply's size like `(120 bytes, 3 elements)`, elements being the length of a
+/// top-level collection or 1 for anything else
+pub fn format_reply_size(response: &RespType, bytes: usize) -> String {
+    let elements = match response {
+        RespType::Arrays(array) => array.len(),
+        RespType::Sets(set) => set.len(),
+        RespType::Maps(map) => map.len(),
+        _ => 1,
+    };
+    format!("({} bytes, {} elements)", bytes, elements)
+}
+
+/// format an integer with comma thousands separators, like `-1,234,567`
+pub fn group_digits(value: i64) -> String {
+    let digits = value.unsigned_abs().to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3 + 1);
+
+    if value < 0 {
+        grouped.push('-');
+    }
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+
+    grouped
+}
+
+/// human readable byte size using 1024 based units like redis, e.g. `1023B`, `1.50M`
+pub fn format_bytes(n: u64) -> String {
+    const UNITS: [&str; 5] = ["K", "M", "G", "T", "P"];
+
+    if n < 1024 {
+        return format!("{}B", n);
+    }
+
+    let mut value = n as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.2}{}", value, UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::byte_buffer::BytesBuffer;
+
+    fn decode(bytes: &[u8]) -> RespType {
+        let mut buff = BytesBuffer::new(64);
+        buff.put_u8_slice(bytes);
+        RespType::decode(&mut buff)
+    }
+
+    #[test]
+    fn commands_resolve_to_their_render_hint() {
+        assert_eq!(RenderHint::for_command("config get *"), RenderHint::Table);
+        assert_eq!(
+            RenderHint::for_command("CONFIG SET a b"),
+            RenderHint::Generic
+        );
+        assert_eq!(RenderHint::for_command("hgetall h"), RenderHint::Table);
+        assert_eq!(RenderHint::for_command("XRANGE s - +"), RenderHint::Stream);
+        assert_eq!(
+            RenderHint::for_command("XCLAIM s g c 0 1-0"),
+            RenderHint::Stream
+        );
+        assert_eq!(
+            RenderHint::for_command("xautoclaim s g c 0 0"),
+            RenderHint::XAutoClaim
+        );
+        assert_eq!(RenderHint::for_command("GEOPOS g a"), RenderHint::Geo);
+        assert_eq!(RenderHint::for_command("GET k"), RenderHint::Generic);
+        assert_eq!(RenderHint::for_command(""), RenderHint::Generic);
+    }
+
+    #[test]
+    fn render_names_and_overrides() {
+        assert_eq!(RenderHint::from_name("Table"), Some(RenderHint::Table));
+        assert_eq!(
+            RenderHint::from_name("xautoclaim"),
+            Some(RenderHint::XAutoClaim)
+        );
+        assert_eq!(RenderHint::from_name("bogus"), None);
+
+        let forced = OutputOptions {
+            render: Some(RenderHint::Generic),
+            ..OutputOptions::default()
+        };
+        assert_eq!(
+            RenderHint::resolve("HGETALL h", &forced),
+            RenderHint::Generic
+        );
+        let raw = OutputOptions {
+            raw: true,
+            ..OutputOptions::default()
+        };
+        assert_eq!(RenderHint::resolve("HGETALL h", &raw), RenderHint::Generic);
+        assert_eq!(
+            RenderHint::resolve("HGETALL h", &OutputOptions::default()),
+            RenderHint::Table
+        );
+    }
+
+    fn cooked(resp: &RespType, options: &OutputOptions) -> String {
+        color::strip(&render(resp, options))
+    }
+
+    #[test]
+    fn nested_arrays_are_indexed_like_redis_cli() {
+        let reply = decode(b"*3\r\n*2\r\n$1\r\na\r\n:5\r\n$2\r\nq\"\r\n*0\r\n");
+        assert_eq!(
+            cooked(&reply, &OutputOptions::default()),
+            "1) 1) \"a\"\n   2) (integer) 5\n2) \"q\\\"\"\n3) (empty array)"
+        );
+
+        let reply = decode(b"%1\r\n$1\r\nk\r\n*2\r\n_\r\n$0\r\n\r\n");
+        // like redis-cli, a map value's elements line up under the entry index
+        assert_eq!(
+            cooked(&reply, &OutputOptions::default()),
+            "1# \"k\" => 1) (nil)\n   2) \"\""
+        );
+    }
+
+    #[test]
+    fn long_arrays_are_cut_at_max_elements() {
+        let mut bytes = b"*100\r\n".to_vec();
+        for i in 0..100 {
+            bytes.extend_from_slice(format!(":{}\r\n", i).as_bytes());
+        }
+        let reply = decode(&bytes);
+        let options = OutputOptions {
+            max_elements: Some(20),
+            ..OutputOptions::default()
+        };
+        let text = cooked(&reply, &options);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 21);
+        assert_eq!(lines[0], " 1) (integer) 0");
+        assert_eq!(lines[19], "20) (integer) 19");
+        assert_eq!(lines[20], "... and 80 more");
+
+        let raw = OutputOptions {
+            raw: true,
+            ..options
+        };
+        let text = render(&reply, &raw);
+        assert_eq!(text.lines().count(), 21);
+        assert!(text.ends_with("19\n... and 80 more\n"));
+    }
+
+    #[test]
+    fn raw_and_cooked_bulk_strings_with_a_newline() {
+        let reply = decode(b"$3\r\na\nb\r\n");
+        let raw = OutputOptions {
+            raw: true,
+            ..OutputOptions::default()
+        };
+        assert_eq!(render(&reply, &raw), "a\nb");
+        assert_eq!(render(&reply, &OutputOptions::default()), "\"a\\nb\"");
+    }
+
+    #[test]
+    fn an_xautoclaim_reply_shows_cursor_and_claimed_entries() {
+        let reply = decode(
+            b"*3\r\n$3\r\n0-0\r\n*1\r\n*2\r\n$3\r\n1-0\r\n*2\r\n$5\r\nfield\r\n$5\r\nvalue\r\n*1\r\n$3\r\n2-0\r\n",
+        );
+        assert_eq!(
+            render_with_hint(&reply, RenderHint::XAutoClaim, &OutputOptions::default()),
+            "cursor: 0-0\nclaimed:\n1) 1-0\n   field: value\ndeleted:\n   2-0\n"
+        );
+    }
+
+    #[test]
+    fn field_value_replies_render_as_tables() {
+        let reply = decode(b"*4\r\n$4\r\nname\r\n$3\r\nbob\r\n$3\r\nage\r\n$2\r\n42\r\n");
+        assert_eq!(
+            render_with_hint(&reply, RenderHint::Table, &OutputOptions::default()),
+            "name  bob\nage   42\n"
+        );
+        let reply = decode(b"*1\r\n*2\r\n,1.5\r\n,2.25\r\n");
+        assert_eq!(
+            render_with_hint(&reply, RenderHint::Geo, &OutputOptions::default()),
+            "1) longitude: 1.5, latitude: 2.25\n"
+        );
+    }
+
+    #[test]
+    fn byte_sizes_use_1024_based_units() {
+        assert_eq!(format_bytes(0), "0B");
+        assert_eq!(format_bytes(1023), "1023B");
+        assert_eq!(format_bytes(1024), "1.00K");
+        assert_eq!(format_bytes(1536), "1.50K");
+        assert_eq!(format_bytes(1048576), "1.00M");
+        assert_eq!(format_bytes(1073741824), "1.00G");
+    }
+
+    #[test]
+    fn timing_and_size_lines() {
+        assert_eq!(format_elapsed(Duration::from_micros(1234)), "(1.23 ms)");
+        assert_eq!(
+            format_reply_size(&decode(b"+OK\r\n"), 5),
+            "(5 bytes, 1 elements)"
+        );
+        assert_eq!(
+            format_reply_size(&decode(b"*2\r\n:1\r\n:2\r\n"), 12),
+            "(12 bytes, 2 elements)"
+        );
+    }
+
+    #[test]
+    fn columns_are_aligned() {
+        let rows = vec![
+            vec!["id".to_string(), "age".to_string(), "cmd".to_string()],
+            vec!["7".to_string(), "12345".to_string(), "get".to_string()],
+        ];
+        assert_eq!(
+            format_columns(&rows, &[1]),
+            "id    age  cmd\n7   12345  get"
+        );
+    }
+
+    #[test]
+    fn quoting_escapes_non_printable_bytes() {
+        assert_eq!(quote(b"plain"), "\"plain\"");
+        assert_eq!(quote(b"a\"b\\c"), "\"a\\\"b\\\\c\"");
+        assert_eq!(quote(b"\r\n\t\x00\xff"), "\"\\r\\n\\t\\x00\\xff\"");
+    }
+
+    #[test]
+    fn digits_are_grouped_by_thousands() {
+        assert_eq!(group_digits(0), "0");
+        assert_eq!(group_digits(999), "999");
+        assert_eq!(group_digits(1000), "1,000");
+        assert_eq!(group_digits(1234567), "1,234,567");
+        assert_eq!(group_digits(-1234567), "-1,234,567");
+        assert_eq!(group_digits(i64::MIN), "-9,223,372,036,854,775,808");
+    }
+
+    #[test]
+    fn raw_output_never_groups_digits() {
+        let reply = decode(b":1234567\r\n");
+        let options = OutputOptions {
+            group_digits: true,
+            raw: true,
+            ..OutputOptions::default()
+        };
+        assert_eq!(render(&reply, &options), "1234567");
+
+        let cooked = OutputOptions {
+            raw: false,
+            ..options
+        };
+        assert!(color::strip(&render(&reply, &cooked)).ends_with("1,234,567"));
+    }
+
+    #[test]
+    fn verbatim_strings_other_than_text_are_labelled() {
+        let options = OutputOptions::default();
+        assert_eq!(render(&decode(b"=9\r\ntxt:hello\r\n"), &options), "hello");
+        assert_eq!(
+            render(&decode(b"=9\r\nmkd:# hi!\r\n"), &options),
+            "(mkd) # hi!"
+        );
+    }
+}
@@ -1,19 +1,75 @@
-use std::{
-    collections::{BTreeMap, HashSet},
-    fmt,
-    hash::Hash,
-};
+use std::{borrow::Cow, collections::BTreeMap, fmt};
 
-use num_bigint::BigInt;
+use zeroize::{Zeroize, Zeroizing};
 
 use crate::byte_buffer::BytesBuffer;
 
 /// redis resp type default terminator
-const TERMINATOR: &'static [u8; 2] = b"\r\n";
+pub const TERMINATOR: &[u8; 2] = b"\r\n";
+
+/// `?` in place of an aggregate's length: elements follow until a `.\r\n` end frame
+const STREAMED_LENGTH: &[u8] = b"?";
+
+/// type byte of the frame ending a streamed aggregate
+const END_MARKER: u8 = b'.';
+
+/// length of a nil bulk string or array in RESP2, `$-1` and `*-1`
+const NIL_LENGTH: &[u8] = b"-1\r\n";
+
+/// the elements of a streamed aggregate, consuming its end frame; `None` if one is malformed
+fn decode_streamed(buff: &mut BytesBuffer) -> Option<Vec<RespType>> {
+    let mut elements = Vec::new();
+    while let Some(&byte) = buff.readable_bytes().first() {
+        if byte == END_MARKER {
+            buff.get_slice_until(TERMINATOR);
+            break;
+        }
+        elements.push(decode_element(buff)?);
+    }
+    Some(elements)
+}
+
+/// `count` elements of an aggregate, `None` if one is malformed
+fn decode_elements(buff: &mut BytesBuffer, count: usize) -> Option<Vec<RespType>> {
+    // a bogus count mustn't allocate more than the buffer could hold
+    let mut elements = Vec::with_capacity(count.min(buff.remaining()));
+    for _ in 0..count {
+        elements.push(decode_element(buff)?);
+    }
+    Some(elements)
+}
+
+fn decode_element(buff: &mut BytesBuffer) -> Option<RespType> {
+    match RespType::decode(buff) {
+        RespType::Unknown => None,
+        element => Some(element),
+    }
+}
+
+/// the number on a header line, `None` when it isn't one
+fn decode_number<T: std::str::FromStr>(buff: &mut BytesBuffer) -> Option<T> {
+    String::from_utf8_lossy(buff.get_slice_until(TERMINATOR))
+        .parse::<T>()
+        .ok()
+}
+
+/// write `<type byte><payload>\r\n`, the shape shared by all single line types
+fn encode_line(buff: &mut BytesBuffer, type_byte: u8, payload: &[u8]) {
+    buff.put_u8(type_byte);
+    buff.put_u8_slice(payload);
+    buff.put_u8_slice(&TERMINATOR[..]);
+}
+
+/// consume the `\r\n` closing a length prefixed payload, when it is there
+fn skip_terminator(buff: &mut BytesBuffer) {
+    if buff.readable_bytes().starts_with(TERMINATOR) {
+        buff.get_slice(TERMINATOR.len());
+    }
+}
 
 /// this redis client support resp version
-#[allow(unused)]
-enum ProtoVer {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProtoVer {
     Resp2,
     Resp3,
 }
@@ -31,10 +87,13 @@ impl ProtoVer {
     }
 }
 
+#[derive(Clone)]
 pub struct Hello {
     username: Option<String>,
     password: Option<String>,
     client_name: String,
+    /// protocol asked for in HELLO
+    proto: ProtoVer,
 }
 
 impl Hello {
@@ -43,48 +102,66 @@ impl Hello {
             username: None,
             password: None,
             client_name: "rredis_cli".to_string(),
+            proto: ProtoVer::newest_ver(),
         }
     }
 
+    pub fn has_password(&self) -> bool {
+        self.password.is_some()
+    }
+
+    /// `-2`/`-3` pick the protocol, RESP3 by default
+    pub fn with_proto(mut self, proto: ProtoVer) -> Hello {
+        self.proto = proto;
+        self
+    }
+
+    pub fn proto(&self) -> ProtoVer {
+        self.proto
+    }
+
     pub fn with_password(username: &str, password: &str) -> Hello {
         Hello {
             username: Some(username.to_string()),
             password: Some(password.to_string()),
             client_name: "rredis_cli".to_string(),
+            proto: ProtoVer::newest_ver(),
         }
     }
 
+    /// `HELLO proto_ver [AUTH username password] SETNAME client_name` as a RESP array,
+    /// so a password with spaces or quotes reaches the server whole
     pub fn encode(&self) -> Vec<u8> {
-        // hello proto_ver [auth username password setname client_name]
-        let mut hello_v = vec![];
-
-        // hello proto_ver
-        hello_v.extend_from_slice(b"HELLO ");
-        hello_v.extend_from_slice(ProtoVer::newest_ver().str_ver().as_bytes());
-        hello_v.push(b' ');
-
-        // auth username password
-        if self.password.is_some() {
-            hello_v.extend_from_slice(b"AUTH ");
-            hello_v.extend_from_slice(
-                self.username
-                    .as_ref()
-                    .unwrap_or(&"default".to_string())
-                    .as_bytes(),
-            );
-            hello_v.push(b' ');
-            hello_v.extend_from_slice(self.password.as_ref().unwrap().as_bytes());
-            hello_v.push(b' ');
+        let mut args: Vec<&[u8]> = vec![b"HELLO", self.proto.str_ver().as_bytes()];
+        if let Some(password) = &self.password {
+            let username = self.username.as_deref().unwrap_or("default");
+            args.extend([b"AUTH", username.as_bytes(), password.as_bytes()]);
         }
+        args.extend([b"SETNAME", self.client_name.as_bytes()]);
 
-        // setname
-        hello_v.extend_from_slice(b"SETNAME ");
-        hello_v.extend_from_slice(self.client_name.as_bytes());
+        let mut buff = BytesBuffer::new(64);
+        RespType::command(&args).encode(&mut buff);
+        buff.readable_bytes().to_vec()
+    }
 
-        // terminator
-        hello_v.extend_from_slice(b"\r\n");
+    /// `AUTH [username] password` for servers without HELLO, the username is left
+    /// out for `default` since servers before 6.0 only take a password
+    pub fn auth_args(&self) -> Option<Zeroizing<Vec<Vec<u8>>>> {
+        let password = self.password.as_ref()?;
+        let mut args = vec![b"AUTH".to_vec()];
+        if let Some(username) = self.username.as_ref().filter(|name| *name != "default") {
+            args.push(username.as_bytes().to_vec());
+        }
+        args.push(password.as_bytes().to_vec());
+        Some(Zeroizing::new(args))
+    }
+}
 
-        hello_v
+impl Drop for Hello {
+    fn drop(&mut self) {
+        if let Some(password) = self.password.as_mut() {
+            password.zeroize();
+        }
     }
 }
 
@@ -102,56 +179,202 @@ pub enum RespType {
     Arrays(Array),
     SimpleErrors(SimpleError),
     BulkErrors(BulkError),
+    VerbatimStrings(VerbatimString),
+    Pushes(Push),
     // local define resp type, no send to server
     Unknown,
 }
 
 impl RespType {
+    /// the first byte of every RESP2 and RESP3 frame
+    pub fn is_type_byte(byte: u8) -> bool {
+        matches!(
+            byte,
+            SimpleString::PLUS
+                | BulkString::DOLLAR
+                | Integer::COLON
+                | Boolean::OCTOTHORPE
+                | Double::COMMA
+                | BigNumber::LEFT_PARENTHESIS
+                | Null::UNDERSCORE
+                | Map::PERCENT
+                | Set::TIDLE
+                | Array::STAR
+                | SimpleError::MINUS
+                | BulkError::EXCLAMATION
+                | VerbatimString::EQUALS
+                | Push::GREATER_THAN
+                | Attribute::PIPE
+        )
+    }
+
+    /// a bare line like `PONG\r\n` from some minimal servers and proxies; only printable
+    /// ascii counts, so binary garbage is still skipped as corruption
+    pub fn is_inline_start(byte: u8) -> bool {
+        !RespType::is_type_byte(byte) && byte.is_ascii_graphic()
+    }
+
+    /// length of a complete inline line in `bytes`, `None` while more data is needed
+    pub fn inline_length(bytes: &[u8]) -> Option<usize> {
+        bytes
+            .windows(TERMINATOR.len())
+            .position(|window| window == TERMINATOR)
+            .map(|end| end + TERMINATOR.len())
+    }
+
+    /// an inline line as a simple string, for a server known to reply inline
+    pub fn decode_inline(buff: &mut BytesBuffer) -> RespType {
+        RespType::SimpleStrings(SimpleString::decode(buff))
+    }
+
+    /// the next frame, `Unknown` when it is malformed, like a length that isn't a number;
+    /// a malformed frame is consumed up to where it went wrong
     pub fn decode(buff: &mut BytesBuffer) -> RespType {
-        let byte = buff.get_u8();
-        match byte {
-            SimpleString::PLUS => RespType::SimpleStrings(SimpleString::decode(buff)),
-            BulkString::DOLLAR => RespType::BulkStrings(BulkString::decode(buff)),
-            Integer::COLON => RespType::Integers(Integer::decode(buff)),
-            Boolean::OCTOTHORPE => RespType::Booleans(Boolean::decode(buff)),
-            Double::COMMA => RespType::Doubles(Double::decode(buff)),
-            BigNumber::LEFT_PARENTHESIS => RespType::BigNumbers(BigNumber::decode(buff)),
-            Null::UNDERSCORE => RespType::Nulls(Null::decode(buff)),
-            Map::PERCENT => RespType::Maps(Map::decode(buff)),
-            Set::TIDLE => RespType::Sets(Set::decode(buff)),
-            Array::STAR => RespType::Arrays(Array::decode(buff)),
-            SimpleError::MINUS => RespType::SimpleErrors(SimpleError::decode(buff)),
-            BulkError::EXCLAMATION => RespType::BulkErrors(BulkError::decode(buff)),
-
-            _ => Self::Unknown,
+        let Some(byte) = buff.get_u8() else {
+            return Self::Unknown;
+        };
+        if matches!(byte, BulkString::DOLLAR | Array::STAR)
+            && buff.readable_bytes().starts_with(NIL_LENGTH)
+        {
+            buff.get_slice(NIL_LENGTH.len());
+            return RespType::Nulls(Null);
+        }
+
+        let decoded = match byte {
+            SimpleString::PLUS => Some(RespType::SimpleStrings(SimpleString::decode(buff))),
+            BulkString::DOLLAR => BulkString::decode(buff).map(RespType::BulkStrings),
+            Integer::COLON => Integer::decode(buff).map(RespType::Integers),
+            Boolean::OCTOTHORPE => Some(RespType::Booleans(Boolean::decode(buff))),
+            Double::COMMA => Double::decode(buff).map(RespType::Doubles),
+            BigNumber::LEFT_PARENTHESIS => BigNumber::decode(buff).map(RespType::BigNumbers),
+            Null::UNDERSCORE => Some(RespType::Nulls(Null::decode(buff))),
+            Map::PERCENT => Map::decode(buff).map(RespType::Maps),
+            Set::TIDLE => Set::decode(buff).map(RespType::Sets),
+            Array::STAR => Array::decode(buff).map(RespType::Arrays),
+            SimpleError::MINUS => Some(RespType::SimpleErrors(SimpleError::decode(buff))),
+            BulkError::EXCLAMATION => BulkError::decode(buff).map(RespType::BulkErrors),
+            VerbatimString::EQUALS => VerbatimString::decode(buff).map(RespType::VerbatimStrings),
+            Push::GREATER_THAN => Push::decode(buff).map(RespType::Pushes),
+            Attribute::PIPE => Some(Attribute::decode(buff)),
+            _ => None,
+        };
+        decoded.unwrap_or(Self::Unknown)
+    }
+
+    /// length of the first complete frame in `bytes`, `None` while more data is needed
+    pub fn frame_length(bytes: &[u8]) -> Option<usize> {
+        let (&type_byte, _) = bytes.split_first()?;
+        // bytes that can't start a frame make one malformed run, up to the next one that can
+        if !RespType::is_type_byte(type_byte) {
+            return Some(
+                bytes
+                    .iter()
+                    .position(|&byte| RespType::is_type_byte(byte))
+                    .unwrap_or(bytes.len()),
+            );
+        }
+
+        let line_end = bytes
+            .windows(TERMINATOR.len())
+            .position(|window| window == TERMINATOR)?;
+        let header = line_end + TERMINATOR.len();
+        let length = || {
+            String::from_utf8_lossy(&bytes[1..line_end])
+                .parse::<i64>()
+                .ok()
+        };
+
+        match type_byte {
+            BulkString::DOLLAR | BulkError::EXCLAMATION | VerbatimString::EQUALS => {
+                match length() {
+                    Some(n) if n >= 0 => {
+                        let end = header + n as usize + TERMINATOR.len();
+                        (bytes.len() >= end).then_some(end)
+                    }
+                    _ => Some(header),
+                }
+            }
+            Array::STAR | Set::TIDLE | Map::PERCENT if &bytes[1..line_end] == STREAMED_LENGTH => {
+                let mut end = header;
+                loop {
+                    let rest = &bytes[end..];
+                    if *rest.first()? == END_MARKER {
+                        let line = rest
+                            .windows(TERMINATOR.len())
+                            .position(|w| w == TERMINATOR)?;
+                        return Some(end + line + TERMINATOR.len());
+                    }
+                    end += RespType::frame_length(rest)?;
+                }
+            }
+            Array::STAR | Set::TIDLE | Push::GREATER_THAN | Map::PERCENT | Attribute::PIPE => {
+                let count = match length() {
+                    Some(n) if n > 0 => n as usize,
+                    _ if type_byte == Attribute::PIPE => 0,
+                    _ => return Some(header),
+                };
+                let elements = match type_byte {
+                    Map::PERCENT => count * 2,
+                    // the pairs, then the reply they describe
+                    Attribute::PIPE => count * 2 + 1,
+                    _ => count,
+                };
+                let mut end = header;
+                for _ in 0..elements {
+                    end += RespType::frame_length(&bytes[end..])?;
+                }
+                Some(end)
+            }
+            // single line types
+            _ => Some(header),
         }
     }
 
-    /// build a RespType from command line input
-    /// like `set hello world` => Array([SimpleString("set"), BulkString("hello"), BulkString("world")])
-    pub fn create_from_command_line(value: &str) -> RespType {
-        let arrays: Vec<RespType> = value
-            .split(" ")
-            .map(|t| RespType::BulkStrings(BulkString::new(t.to_string())))
+    /// a command as an array of binary safe bulk strings
+    /// like `set hello "big world"` => Array([BulkString("set"), BulkString("hello"), BulkString("big world")])
+    pub fn from_args(args: Vec<Vec<u8>>) -> RespType {
+        let arrays: Vec<RespType> = args
+            .into_iter()
+            .map(|t| RespType::BulkStrings(BulkString::new(t)))
             .collect();
 
         RespType::Arrays(Array::new(arrays))
     }
 
+    /// a command built from its arguments, for commands the client sends itself,
+    /// so a key or pattern is never split or unquoted again
+    pub fn command(args: &[&[u8]]) -> RespType {
+        RespType::from_args(args.iter().map(|arg| arg.to_vec()).collect())
+    }
+
     pub fn encode(&self, buff: &mut BytesBuffer) {
         match self {
-            RespType::Arrays(array) => array.encode(buff),
+            RespType::SimpleStrings(simple_string) => simple_string.encode(buff),
             RespType::BulkStrings(bulk_string) => bulk_string.encode(buff),
+            RespType::Integers(integer) => integer.encode(buff),
+            RespType::Booleans(boolean) => boolean.encode(buff),
+            RespType::Doubles(double) => double.encode(buff),
+            RespType::BigNumbers(big_number) => big_number.encode(buff),
+            RespType::Nulls(null) => null.encode(buff),
+            RespType::Maps(map) => map.encode(buff),
+            RespType::Sets(set) => set.encode(buff),
+            RespType::Arrays(array) => array.encode(buff),
+            RespType::SimpleErrors(simple_error) => simple_error.encode(buff),
+            RespType::BulkErrors(bulk_error) => bulk_error.encode(buff),
+            RespType::VerbatimStrings(verbatim_string) => verbatim_string.encode(buff),
+            RespType::Pushes(push) => push.encode(buff),
 
-            _ => panic!("Invalid resp type"),
+            RespType::Unknown => panic!("Invalid resp type"),
         }
     }
 
     pub fn is_err_type(&self) -> bool {
-        match self {
-            RespType::SimpleErrors(_) | RespType::BulkErrors(_) => true,
-            _ => false,
-        }
+        matches!(self, RespType::SimpleErrors(_) | RespType::BulkErrors(_))
+    }
+
+    /// out of band data from the server, like pub/sub messages, not a command reply
+    pub fn is_push(&self) -> bool {
+        matches!(self, RespType::Pushes(_))
     }
 }
 
@@ -159,15 +382,15 @@ impl fmt::Display for RespType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             RespType::SimpleStrings(ss) => write!(f, "{}", ss.value),
-            RespType::BulkStrings(bs) => write!(f, "{}", bs.value),
+            RespType::BulkStrings(bs) => write!(f, "{}", bs.value_lossy()),
             RespType::Integers(i) => write!(f, "{}", i.value),
             RespType::Booleans(b) => write!(f, "{}", b.value),
-            RespType::Doubles(d) => write!(f, "{}", d.value),
+            RespType::Doubles(d) => write!(f, "{}", d.digits()),
             RespType::BigNumbers(bn) => write!(f, "{}", bn.value),
-            RespType::Nulls(_) => write!(f, "{}", "nil"),
+            RespType::Nulls(_) => write!(f, "nil"),
             RespType::Maps(m) => {
                 if m.map.is_empty() {
-                    return write!(f, "{}", "{}");
+                    return write!(f, "{{}}");
                 }
 
                 m.map.iter().for_each(|(key, value)| {
@@ -177,15 +400,15 @@ impl fmt::Display for RespType {
             }
             RespType::Sets(s) => {
                 if s.value.is_empty() {
-                    return write!(f, "{}", "#{}");
+                    return write!(f, "#{{}}");
                 }
 
-                s.value.iter().for_each(|e| write!(f, "{}", e.1).unwrap());
+                s.value.iter().for_each(|e| writeln!(f, "{}", e).unwrap());
                 fmt::Result::Ok(())
             }
             RespType::Arrays(a) => {
                 if a.value.is_empty() {
-                    return write!(f, "{}", "[]");
+                    return write!(f, "[]");
                 }
 
                 a.value.iter().for_each(|e| writeln!(f, "{}", e).unwrap());
@@ -193,75 +416,101 @@ impl fmt::Display for RespType {
             }
             RespType::SimpleErrors(se) => write!(f, "{}", se.value),
             RespType::BulkErrors(be) => write!(f, "{}", be.value),
+            RespType::VerbatimStrings(vs) => write!(f, "{}", vs.value),
+            RespType::Pushes(p) => {
+                p.iter().for_each(|e| writeln!(f, "{}", e).unwrap());
+                fmt::Result::Ok(())
+            }
             RespType::Unknown => write!(f, "Unknown Response"),
         }
     }
 }
 
 pub struct SimpleString {
-    pub value: String,
+    value: String,
 }
 
 impl SimpleString {
     const PLUS: u8 = b'+';
 
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
     pub fn decode(buff: &mut BytesBuffer) -> SimpleString {
         let string_bytes = buff.get_slice_until(TERMINATOR);
         SimpleString {
             value: String::from_utf8_lossy(string_bytes).to_string(),
         }
     }
+
+    pub fn encode(&self, buff: &mut BytesBuffer) {
+        encode_line(buff, SimpleString::PLUS, self.value.as_bytes());
+    }
 }
 
-/// $<length>\r\n<data>\r\n
+/// $<length>\r\n<data>\r\n, the data is kept as raw bytes since it may be binary
 pub struct BulkString {
-    pub value: String,
+    value: Vec<u8>,
 }
 
 impl BulkString {
     const DOLLAR: u8 = b'$';
 
-    pub fn new(value: String) -> BulkString {
+    pub fn new(value: Vec<u8>) -> BulkString {
         BulkString { value }
     }
 
-    pub fn decode(buff: &mut BytesBuffer) -> BulkString {
-        // length
-        let bytes_length = String::from_utf8_lossy(buff.get_slice_until(TERMINATOR))
-            .parse::<usize>()
-            .unwrap();
+    /// the raw data, which may be binary
+    pub fn value(&self) -> &[u8] {
+        &self.value
+    }
+
+    /// the data for display, invalid utf-8 is replaced
+    pub fn value_lossy(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.value)
+    }
+
+    /// `None` for a malformed length, the nil `$-1` is decoded as a null by `RespType`
+    pub fn decode(buff: &mut BytesBuffer) -> Option<BulkString> {
+        let bytes_length = decode_number::<usize>(buff)?;
 
         // read data
-        let value = String::from_utf8_lossy(buff.get_slice(bytes_length)).to_string();
+        let value = buff.get_slice(bytes_length).to_vec();
 
-        // terminator
-        buff.get_u8();
-        buff.get_u8();
+        skip_terminator(buff);
 
-        BulkString { value }
+        Some(BulkString { value })
     }
 
     pub fn encode(&self, buff: &mut BytesBuffer) {
         buff.put_u8(BulkString::DOLLAR);
         buff.put_u8_slice(self.value.len().to_string().as_bytes());
         buff.put_u8_slice(&TERMINATOR[..]);
-        buff.put_u8_slice(self.value.as_bytes());
+        buff.put_u8_slice(&self.value);
         buff.put_u8_slice(&TERMINATOR[..]);
     }
 }
 
 pub struct Integer {
-    pub value: isize,
+    value: isize,
 }
 
 impl Integer {
     const COLON: u8 = b':';
 
-    pub fn decode(buff: &mut BytesBuffer) -> Integer {
-        let digits = String::from_utf8_lossy(buff.get_slice_until(TERMINATOR));
-        Integer {
-            value: digits.parse::<isize>().unwrap(),
-        }
+    pub fn value(&self) -> isize {
+        self.value
+    }
+
+    pub fn decode(buff: &mut BytesBuffer) -> Option<Integer> {
+        Some(Integer {
+            value: decode_number(buff)?,
+        })
+    }
+
+    pub fn encode(&self, buff: &mut BytesBuffer) {
+        encode_line(buff, Integer::COLON, self.value.to_string().as_bytes());
     }
 }
 
@@ -273,15 +522,17 @@ impl Boolean {
     const OCTOTHORPE: u8 = b'#';
 
     pub fn decode(buff: &mut BytesBuffer) -> Boolean {
-        let b_byte = buff.get_u8();
-
-        // terminal
-        buff.get_u8();
-        buff.get_u8();
-
-        let value = if b't' == b_byte { true } else { false };
+        let value = buff.get_slice_until(TERMINATOR) == b"t";
         Boolean { value }
     }
+
+    pub fn encode(&self, buff: &mut BytesBuffer) {
+        encode_line(
+            buff,
+            Boolean::OCTOTHORPE,
+            if self.value { b"t" } else { b"f" },
+        );
+    }
 }
 
 pub struct Double {
@@ -291,26 +542,50 @@ pub struct Double {
 impl Double {
     const COMMA: u8 = b',';
 
-    pub fn decode(buff: &mut BytesBuffer) -> Double {
-        let digits = String::from_utf8_lossy(buff.get_slice_until(TERMINATOR));
-        Double {
-            value: digits.parse::<f64>().unwrap(),
+    /// `,<floating-point-number>\r\n`, also `,inf`, `,-inf` and `,nan`
+    pub fn decode(buff: &mut BytesBuffer) -> Option<Double> {
+        Some(Double {
+            value: decode_number(buff)?,
+        })
+    }
+
+    pub fn encode(&self, buff: &mut BytesBuffer) {
+        encode_line(buff, Double::COMMA, self.digits().as_bytes());
+    }
+
+    /// the resp spelling, `inf`, `-inf` and `nan` included; rust would print `NaN`
+    pub fn digits(&self) -> String {
+        if self.value.is_nan() {
+            "nan".to_string()
+        } else {
+            self.value.to_string()
         }
     }
 }
 
+/// kept as the raw digits since it may exceed any native integer
 pub struct BigNumber {
-    value: BigInt,
+    value: String,
 }
 
 impl BigNumber {
     const LEFT_PARENTHESIS: u8 = b'(';
 
-    pub fn decode(buff: &mut BytesBuffer) -> BigNumber {
+    /// `None` unless the line is an optionally signed run of digits
+    pub fn decode(buff: &mut BytesBuffer) -> Option<BigNumber> {
         let digits = String::from_utf8_lossy(buff.get_slice_until(TERMINATOR));
-        BigNumber {
-            value: digits.parse::<BigInt>().unwrap(),
+        let unsigned = digits.strip_prefix(['-', '+']).unwrap_or(&digits);
+        if unsigned.is_empty() || !unsigned.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
         }
+
+        Some(BigNumber {
+            value: digits.to_string(),
+        })
+    }
+
+    pub fn encode(&self, buff: &mut BytesBuffer) {
+        encode_line(buff, BigNumber::LEFT_PARENTHESIS, self.value.as_bytes());
     }
 }
 
@@ -320,25 +595,26 @@ impl Null {
     const UNDERSCORE: u8 = b'_';
 
     pub fn decode(buff: &mut BytesBuffer) -> Null {
-        // terminal
-        buff.get_u8();
-        buff.get_u8();
-
+        buff.get_slice_until(TERMINATOR);
         Null
     }
+
+    pub fn encode(&self, buff: &mut BytesBuffer) {
+        encode_line(buff, Null::UNDERSCORE, b"");
+    }
 }
 
 pub struct OrderKey(usize, RespType);
 
 impl PartialOrd for OrderKey {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.0.partial_cmp(&other.0)
+        Some(self.cmp(other))
     }
 }
 
 impl Ord for OrderKey {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.0.partial_cmp(&other.0).unwrap()
+        self.0.cmp(&other.0)
     }
 }
 
@@ -350,12 +626,6 @@ impl PartialEq for OrderKey {
 
 impl Eq for OrderKey {}
 
-impl Hash for OrderKey {
-    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        self.0.hash(state);
-    }
-}
-
 pub struct Map {
     map: BTreeMap<OrderKey, RespType>,
 }
@@ -363,50 +633,120 @@ pub struct Map {
 impl Map {
     const PERCENT: u8 = b'%';
 
-    pub fn decode(buff: &mut BytesBuffer) -> Map {
+    pub fn decode(buff: &mut BytesBuffer) -> Option<Map> {
         // length number of elements
-        let noe = String::from_utf8_lossy(buff.get_slice_until(TERMINATOR))
-            .parse::<usize>()
-            .unwrap();
-
+        let length = buff.get_slice_until(TERMINATOR);
+        let elements = if length == STREAMED_LENGTH {
+            decode_streamed(buff)?
+        } else {
+            let noe = String::from_utf8_lossy(length).parse::<usize>().ok()?;
+            decode_elements(buff, noe.checked_mul(2)?)?
+        };
+
+        let mut elements = elements.into_iter();
         let mut map = BTreeMap::new();
-        // read terminal
-        for i in 0..noe {
-            let key = RespType::decode(buff);
-            let value = RespType::decode(buff);
-
+        let mut i = 0;
+        while let (Some(key), Some(value)) = (elements.next(), elements.next()) {
             map.insert(OrderKey(i, key), value);
+            i += 1;
+        }
+        Some(Map { map })
+    }
+
+    /// entries are written in insertion order, following the `OrderKey` index
+    pub fn encode(&self, buff: &mut BytesBuffer) {
+        encode_line(buff, Map::PERCENT, self.map.len().to_string().as_bytes());
+        for (key, value) in self.iter() {
+            key.encode(buff);
+            value.encode(buff);
         }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// iterate entries in insertion order
+    pub fn iter(&self) -> impl Iterator<Item = (&RespType, &RespType)> {
+        self.map.iter().map(|(key, value)| (&key.1, value))
+    }
 
-        Map { map }
+    /// the value of the first entry whose simple or bulk string key is `key`
+    pub fn get(&self, key: &str) -> Option<&RespType> {
+        self.iter()
+            .find(|(entry_key, _)| match entry_key {
+                RespType::BulkStrings(bulk) => bulk.value == key.as_bytes(),
+                RespType::SimpleStrings(simple) => simple.value == key,
+                _ => false,
+            })
+            .map(|(_, value)| value)
     }
 }
 
+/// |<count>\r\n<key><value>... ahead of a reply, out-of-band details like client side
+/// caching hints; the pairs are read and dropped, the reply after them is returned
+pub struct Attribute;
+
+impl Attribute {
+    const PIPE: u8 = b'|';
+
+    pub fn decode(buff: &mut BytesBuffer) -> RespType {
+        match Map::decode(buff) {
+            Some(_) => RespType::decode(buff),
+            None => RespType::Unknown,
+        }
+    }
+}
+
+/// set members in the order the server sent them, so display is reproducible
 pub struct Set {
-    value: HashSet<OrderKey>,
+    value: Vec<RespType>,
 }
 
 impl Set {
     const TIDLE: u8 = b'~';
 
-    pub fn decode(buff: &mut BytesBuffer) -> Set {
+    pub fn decode(buff: &mut BytesBuffer) -> Option<Set> {
         // number of elements
-        let noe = String::from_utf8_lossy(buff.get_slice_until(TERMINATOR))
-            .parse::<usize>()
-            .unwrap();
-
-        let mut value = HashSet::with_capacity(noe);
-        // read elements
-        for i in 0..noe {
-            value.insert(OrderKey(i, RespType::decode(buff)));
+        let length = buff.get_slice_until(TERMINATOR);
+        if length == STREAMED_LENGTH {
+            return Some(Set {
+                value: decode_streamed(buff)?,
+            });
+        }
+        let noe = String::from_utf8_lossy(length).parse::<usize>().ok()?;
+
+        Some(Set {
+            value: decode_elements(buff, noe)?,
+        })
+    }
+
+    pub fn encode(&self, buff: &mut BytesBuffer) {
+        encode_line(buff, Set::TIDLE, self.value.len().to_string().as_bytes());
+        for item in &self.value {
+            item.encode(buff);
         }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.value.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.value.len()
+    }
 
-        Set { value }
+    pub fn iter(&self) -> impl Iterator<Item = &RespType> {
+        self.value.iter()
     }
 }
 
 pub struct Array {
-    pub value: Vec<RespType>,
+    value: Vec<RespType>,
 }
 
 impl Array {
@@ -416,19 +756,46 @@ impl Array {
         Array { value }
     }
 
-    pub fn decode(buff: &mut BytesBuffer) -> Array {
+    pub fn is_empty(&self) -> bool {
+        self.value.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.value.len()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&RespType> {
+        self.value.get(index)
+    }
+
+    pub fn as_slice(&self) -> &[RespType] {
+        &self.value
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &RespType> {
+        self.value.iter()
+    }
+
+    /// the elements, taking them out of the array
+    pub fn into_vec(self) -> Vec<RespType> {
+        self.value
+    }
+
+    /// `None` for a malformed length or element, the nil `*-1` is decoded as a null
+    /// by `RespType`
+    pub fn decode(buff: &mut BytesBuffer) -> Option<Array> {
         // number of elements
-        let noe = String::from_utf8_lossy(buff.get_slice_until(TERMINATOR))
-            .parse::<usize>()
-            .unwrap();
-
-        let mut value = Vec::with_capacity(noe);
-        // read terminal
-        for _ in 0..noe {
-            value.push(RespType::decode(buff));
+        let length = buff.get_slice_until(TERMINATOR);
+        if length == STREAMED_LENGTH {
+            return Some(Array {
+                value: decode_streamed(buff)?,
+            });
         }
+        let noe = String::from_utf8_lossy(length).parse::<usize>().ok()?;
 
-        Array { value }
+        Some(Array {
+            value: decode_elements(buff, noe)?,
+        })
     }
 
     pub fn encode(&self, buff: &mut BytesBuffer) {
@@ -453,6 +820,10 @@ impl SimpleError {
         let value = String::from_utf8_lossy(buff.get_slice_until(TERMINATOR)).to_string();
         SimpleError { value }
     }
+
+    pub fn encode(&self, buff: &mut BytesBuffer) {
+        encode_line(buff, SimpleError::MINUS, self.value.as_bytes());
+    }
 }
 
 pub struct BulkError {
@@ -462,19 +833,438 @@ pub struct BulkError {
 impl BulkError {
     const EXCLAMATION: u8 = b'!';
 
-    pub fn decode(buff: &mut BytesBuffer) -> BulkError {
+    pub fn decode(buff: &mut BytesBuffer) -> Option<BulkError> {
         // length
-        let bytes_length = String::from_utf8_lossy(buff.get_slice_until(TERMINATOR))
-            .parse::<usize>()
-            .unwrap();
+        let bytes_length = decode_number::<usize>(buff)?;
 
         // read data
         let value = String::from_utf8_lossy(buff.get_slice(bytes_length)).to_string();
 
-        // terminator
-        buff.get_u8();
-        buff.get_u8();
+        skip_terminator(buff);
+
+        Some(BulkError { value })
+    }
+
+    pub fn encode(&self, buff: &mut BytesBuffer) {
+        buff.put_u8(BulkError::EXCLAMATION);
+        buff.put_u8_slice(self.value.len().to_string().as_bytes());
+        buff.put_u8_slice(&TERMINATOR[..]);
+        buff.put_u8_slice(self.value.as_bytes());
+        buff.put_u8_slice(&TERMINATOR[..]);
+    }
+}
+
+/// =<length>\r\n<fmt>:<data>\r\n, the 3 byte format is like `txt` or `mkd`
+pub struct VerbatimString {
+    format: String,
+    value: String,
+}
+
+impl VerbatimString {
+    const EQUALS: u8 = b'=';
+
+    pub fn decode(buff: &mut BytesBuffer) -> Option<VerbatimString> {
+        // length, counting the `fmt:` prefix
+        let bytes_length = decode_number::<usize>(buff)?;
+
+        // read data
+        let data = buff.get_slice(bytes_length);
+        let (format, value) = data.split_at(4.min(data.len()));
+        let format =
+            String::from_utf8_lossy(format.strip_suffix(b":").unwrap_or(format)).to_string();
+        let value = String::from_utf8_lossy(value).to_string();
+
+        skip_terminator(buff);
+
+        Some(VerbatimString { format, value })
+    }
+
+    pub fn encode(&self, buff: &mut BytesBuffer) {
+        buff.put_u8(VerbatimString::EQUALS);
+        buff.put_u8_slice(
+            (self.format.len() + 1 + self.value.len())
+                .to_string()
+                .as_bytes(),
+        );
+        buff.put_u8_slice(&TERMINATOR[..]);
+        buff.put_u8_slice(self.format.as_bytes());
+        buff.put_u8(b':');
+        buff.put_u8_slice(self.value.as_bytes());
+        buff.put_u8_slice(&TERMINATOR[..]);
+    }
+
+    /// the 3 byte format, like `txt` or `mkd`
+    pub fn format(&self) -> &str {
+        &self.format
+    }
+
+    /// the text after the `fmt:` prefix
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+}
+
+/// ><number-of-elements>\r\n<element-1>...<element-n>, sent by the server unasked
+pub struct Push {
+    elements: Vec<RespType>,
+}
+
+impl Push {
+    const GREATER_THAN: u8 = b'>';
+
+    pub fn elements(&self) -> &[RespType] {
+        &self.elements
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &RespType> {
+        self.elements.iter()
+    }
+
+    pub fn decode(buff: &mut BytesBuffer) -> Option<Push> {
+        Some(Push {
+            elements: Array::decode(buff)?.value,
+        })
+    }
+
+    pub fn encode(&self, buff: &mut BytesBuffer) {
+        encode_line(
+            buff,
+            Push::GREATER_THAN,
+            self.elements.len().to_string().as_bytes(),
+        );
+        for item in &self.elements {
+            item.encode(buff);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(bytes: &[u8]) -> RespType {
+        let mut buff = BytesBuffer::new(64);
+        buff.put_u8_slice(bytes);
+        RespType::decode(&mut buff)
+    }
+
+    fn encode(resp: &RespType) -> Vec<u8> {
+        let mut buff = BytesBuffer::new(64);
+        resp.encode(&mut buff);
+        buff.readable_bytes().to_vec()
+    }
+
+    #[test]
+    fn every_type_encodes_back_to_the_bytes_it_was_decoded_from() {
+        for bytes in [
+            &b"+OK\r\n"[..],
+            b"$5\r\nhello\r\n",
+            b"$0\r\n\r\n",
+            b":-42\r\n",
+            b"#t\r\n",
+            b"#f\r\n",
+            b",3.14\r\n",
+            b",inf\r\n",
+            b",nan\r\n",
+            b"(3492890328409238509324850943850943825024385\r\n",
+            b"_\r\n",
+            b"%2\r\n+first\r\n:1\r\n+second\r\n:2\r\n",
+            b"~2\r\n+b\r\n+a\r\n",
+            b"*3\r\n:1\r\n*1\r\n+nested\r\n_\r\n",
+            b"*0\r\n",
+            b"-ERR unknown command\r\n",
+            b"!21\r\nSYNTAX invalid syntax\r\n",
+            b"=15\r\ntxt:Some string\r\n",
+            b">2\r\n+message\r\n$2\r\nhi\r\n",
+        ] {
+            let mut buff = BytesBuffer::new(64);
+            buff.put_u8_slice(bytes);
+            let decoded = RespType::decode(&mut buff);
+            assert_eq!(buff.remaining(), 0, "{}", String::from_utf8_lossy(bytes));
+            assert_eq!(
+                encode(&decoded),
+                bytes,
+                "{}",
+                String::from_utf8_lossy(bytes)
+            );
+        }
+    }
+
+    #[test]
+    fn doubles_display_like_redis() {
+        assert_eq!(decode(b",3.14\r\n").to_string(), "3.14");
+        assert_eq!(decode(b",inf\r\n").to_string(), "inf");
+        assert_eq!(decode(b",-inf\r\n").to_string(), "-inf");
+        assert_eq!(decode(b",nan\r\n").to_string(), "nan");
+    }
+
+    #[test]
+    fn big_numbers_keep_every_digit() {
+        let RespType::BigNumbers(number) =
+            decode(b"(3492890328409238509324850943850943825024385\r\n")
+        else {
+            panic!("not a big number");
+        };
+        assert_eq!(number.value, "3492890328409238509324850943850943825024385");
+    }
+
+    #[test]
+    fn bulk_strings_are_binary_safe() {
+        let RespType::BulkStrings(bulk) = decode(b"$3\r\n\x00\xff\n\r\n") else {
+            panic!("not a bulk string");
+        };
+        assert_eq!(bulk.value(), [0x00, 0xFF, 0x0A]);
+
+        let stored = BulkString::new(vec![0x00, 0xFF, 0x0A]);
+        assert_eq!(stored.value(), [0x00, 0xFF, 0x0A]);
+    }
+
+    #[test]
+    fn an_empty_bulk_string_is_not_nil() {
+        let RespType::BulkStrings(bulk) = decode(b"$0\r\n\r\n") else {
+            panic!("not a bulk string");
+        };
+        assert!(bulk.value().is_empty());
+        assert!(matches!(decode(b"*0\r\n"), RespType::Arrays(a) if a.is_empty()));
+    }
+
+    #[test]
+    fn inline_lines_decode_as_simple_strings() {
+        assert_eq!(RespType::inline_length(b"PONG\r\n+OK"), Some(6));
+        assert_eq!(RespType::inline_length(b"PON"), None);
+        assert!(RespType::is_inline_start(b'P'));
+        assert!(!RespType::is_inline_start(b'+'));
+        assert!(!RespType::is_inline_start(0x00));
+
+        let mut buff = BytesBuffer::new(64);
+        buff.put_u8_slice(b"PONG\r\n");
+        assert!(matches!(
+            RespType::decode_inline(&mut buff),
+            RespType::SimpleStrings(pong) if pong.value() == "PONG"
+        ));
+    }
+
+    #[test]
+    fn map_values_are_found_by_key() {
+        let RespType::Maps(map) = decode(b"%2\r\n$4\r\nname\r\n$3\r\nbob\r\n+age\r\n:42\r\n")
+        else {
+            panic!("not a map");
+        };
+        assert_eq!(map.len(), 2);
+        assert_eq!(
+            map.get("name").map(RespType::to_string).as_deref(),
+            Some("bob")
+        );
+        assert_eq!(
+            map.get("age").map(RespType::to_string).as_deref(),
+            Some("42")
+        );
+        assert!(map.get("missing").is_none());
+    }
+
+    #[test]
+    fn sets_display_in_the_order_the_server_sent() {
+        let bytes = b"~4\r\n+d\r\n+a\r\n+c\r\n+b\r\n";
+        let first = decode(bytes).to_string();
+        assert_eq!(first, "d\na\nc\nb\n");
+        assert_eq!(decode(bytes).to_string(), first);
+    }
+
+    #[test]
+    fn attributes_are_dropped_before_the_reply() {
+        let bytes = b"|1\r\n+ttl\r\n:10\r\n$5\r\nhello\r\n";
+        assert_eq!(RespType::frame_length(bytes), Some(bytes.len()));
+        assert_eq!(RespType::frame_length(&bytes[..bytes.len() - 3]), None);
+        assert!(matches!(
+            decode(bytes),
+            RespType::BulkStrings(bulk) if bulk.value() == b"hello"
+        ));
+    }
+
+    #[test]
+    fn streamed_aggregates_decode_until_the_end_frame() {
+        let bytes = b"*?\r\n:1\r\n:2\r\n.\r\n";
+        assert_eq!(RespType::frame_length(bytes), Some(bytes.len()));
+        assert_eq!(RespType::frame_length(&bytes[..bytes.len() - 3]), None);
+
+        let RespType::Arrays(array) = decode(bytes) else {
+            panic!("not an array");
+        };
+        assert_eq!(array.len(), 2);
+        assert_eq!(encode(&RespType::Arrays(array)), b"*2\r\n:1\r\n:2\r\n");
+
+        assert!(matches!(decode(b"%?\r\n+k\r\n:1\r\n.\r\n"), RespType::Maps(m) if m.len() == 1));
+    }
+
+    #[test]
+    fn frame_length_waits_for_the_whole_frame() {
+        assert_eq!(RespType::frame_length(b""), None);
+        assert_eq!(RespType::frame_length(b"+OK"), None);
+        assert_eq!(RespType::frame_length(b"+OK\r\n+PONG\r\n"), Some(5));
+        assert_eq!(RespType::frame_length(b"$5\r\nhel"), None);
+        assert_eq!(RespType::frame_length(b"$5\r\nhello\r\n:1\r\n"), Some(11));
+        assert_eq!(RespType::frame_length(b"$-1\r\n"), Some(5));
+        assert_eq!(RespType::frame_length(b"*2\r\n:1\r\n"), None);
+        assert_eq!(
+            RespType::frame_length(b"*2\r\n:1\r\n*1\r\n:2\r\n"),
+            Some(16)
+        );
+        assert_eq!(RespType::frame_length(b"%1\r\n+k\r\n:1\r\n"), Some(12));
+        // garbage runs up to the next type byte
+        assert_eq!(RespType::frame_length(b"\x00\x01+OK\r\n"), Some(2));
+    }
+
+    #[test]
+    fn hello_asks_for_the_chosen_protocol() {
+        assert_eq!(
+            Hello::no_auth().with_proto(ProtoVer::Resp2).encode(),
+            b"*4\r\n$5\r\nHELLO\r\n$1\r\n2\r\n$7\r\nSETNAME\r\n$10\r\nrredis_cli\r\n"
+        );
+        assert_eq!(
+            Hello::no_auth().encode(),
+            b"*4\r\n$5\r\nHELLO\r\n$1\r\n3\r\n$7\r\nSETNAME\r\n$10\r\nrredis_cli\r\n"
+        );
+        let hello = Hello::with_password("default", "secret").with_proto(ProtoVer::Resp2);
+        assert_eq!(hello.proto(), ProtoVer::Resp2);
+        assert_eq!(
+            hello.encode(),
+            b"*7\r\n$5\r\nHELLO\r\n$1\r\n2\r\n$4\r\nAUTH\r\n$7\r\ndefault\r\n\
+              $6\r\nsecret\r\n$7\r\nSETNAME\r\n$10\r\nrredis_cli\r\n"
+        );
+        let hello = Hello::with_password("bob", "two words");
+        assert!(
+            hello
+                .encode()
+                .ends_with(b"$9\r\ntwo words\r\n$7\r\nSETNAME\r\n$10\r\nrredis_cli\r\n")
+        );
+    }
+
+    #[test]
+    fn auth_leaves_out_the_default_user() {
+        let args = |hello: Hello| hello.auth_args().map(|args| args.to_vec());
+        assert_eq!(args(Hello::no_auth()), None);
+        assert_eq!(
+            args(Hello::with_password("default", "pw")),
+            Some(vec![b"AUTH".to_vec(), b"pw".to_vec()])
+        );
+        assert_eq!(
+            args(Hello::with_password("alice", "pw")),
+            Some(vec![b"AUTH".to_vec(), b"alice".to_vec(), b"pw".to_vec()])
+        );
+    }
+
+    #[test]
+    fn commands_are_arrays_of_bulk_strings() {
+        assert_eq!(
+            encode(&RespType::command(&[b"SET", b"k", b"big world"])),
+            b"*3\r\n$3\r\nSET\r\n$1\r\nk\r\n$9\r\nbig world\r\n"
+        );
+    }
+
+    #[test]
+    fn typed_commands_match_the_parsed_command_line() {
+        for (args, line) in [
+            (&[&b"PING"[..]][..], "PING"),
+            (&[b"COMMAND", b"DOCS", b"get"], "COMMAND DOCS get"),
+            (&[b"SCAN", b"0", b"COUNT", b"100"], "SCAN 0 COUNT 100"),
+            (&[b"SET", b"a key", b"v"], "SET \"a key\" v"),
+        ] {
+            let parsed = RespType::from_args(crate::tokenizer::split_args(line).unwrap());
+            assert_eq!(
+                encode(&RespType::command(args)),
+                encode(&parsed),
+                "{}",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn malformed_lengths_decode_as_unknown() {
+        for bytes in [
+            &b"$x\r\n"[..],
+            b"$-2\r\n",
+            b"*x\r\n",
+            b"%1\r\n:x\r\n:1\r\n",
+            b"!x\r\n",
+            b"=x\r\n",
+            b">x\r\n",
+        ] {
+            assert!(
+                matches!(decode(bytes), RespType::Unknown),
+                "{}",
+                String::from_utf8_lossy(bytes)
+            );
+        }
+    }
+
+    #[test]
+    fn malformed_numbers_decode_as_unknown() {
+        for bytes in [
+            &b":12a\r\n"[..],
+            b",1.5.2\r\n",
+            b"(12a\r\n",
+            b"(\r\n",
+            b"(-\r\n",
+        ] {
+            assert!(matches!(decode(bytes), RespType::Unknown));
+        }
+        assert_eq!(
+            decode(b"(-1234567890123456789012\r\n").to_string(),
+            "-1234567890123456789012"
+        );
+    }
+
+    #[test]
+    fn nil_bulk_strings_and_arrays_decode_as_null() {
+        assert!(matches!(decode(b"$-1\r\n"), RespType::Nulls(_)));
+        assert!(matches!(decode(b"*-1\r\n"), RespType::Nulls(_)));
+    }
+
+    #[test]
+    fn verbatim_strings_keep_their_format() {
+        let RespType::VerbatimStrings(vs) = decode(b"=15\r\ntxt:Some string\r\n") else {
+            panic!("not a verbatim string");
+        };
+        assert_eq!(vs.format(), "txt");
+        assert_eq!(vs.value(), "Some string");
+    }
+
+    #[test]
+    fn accessors_reach_the_decoded_values() {
+        let RespType::Arrays(array) = decode(b"*3\r\n$3\r\nkey\r\n+OK\r\n:42\r\n") else {
+            panic!("not an array");
+        };
+        assert!(!array.is_empty());
+        assert_eq!(array.len(), 3);
+        assert_eq!(array.iter().count(), array.as_slice().len());
+        let (Some(RespType::BulkStrings(bs)), Some(RespType::SimpleStrings(ss))) =
+            (array.get(0), array.get(1))
+        else {
+            panic!("unexpected elements");
+        };
+        assert_eq!(bs.value(), b"key");
+        assert_eq!(ss.value(), "OK");
+        assert!(matches!(array.get(2), Some(RespType::Integers(i)) if i.value() == 42));
+
+        let RespType::Sets(set) = decode(b"~1\r\n+a\r\n") else {
+            panic!("not a set");
+        };
+        assert_eq!(set.iter().count(), 1);
+        let RespType::Maps(map) = decode(b"%1\r\n+k\r\n:1\r\n") else {
+            panic!("not a map");
+        };
+        assert_eq!(map.iter().count(), 1);
+        assert!(map.get("k").is_some());
+        let RespType::Pushes(push) = decode(b">2\r\n+message\r\n+x\r\n") else {
+            panic!("not a push");
+        };
+        assert_eq!(push.elements().len(), push.iter().count());
+    }
 
-        BulkError { value }
+    #[test]
+    fn a_malformed_element_makes_the_aggregate_malformed() {
+        assert!(matches!(decode(b"*2\r\n:1\r\n:x\r\n"), RespType::Unknown));
+        assert!(matches!(decode(b"~?\r\n:x\r\n.\r\n"), RespType::Unknown));
     }
 }
@@ -0,0 +1,1516 @@
+use std::{
+    fs,
+    io::{self, BufRead, Write},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    bigkeys,
+    browse::BrowseApp,
+    clients::{self, SortBy},
+    cluster::{self, Cluster},
+    color,
+    command_cache::CommandCache,
+    help,
+    history::{self, History},
+    info, interrupt,
+    latency::LatencyStats,
+    memory,
+    output::{self, OutputOptions, RenderHint},
+    redis_client::{self, RedisClient, ScanEnd, ScanFilter, ServerInfo},
+    redis_type::RespType,
+    slowlog, tokenizer,
+    transcript::Transcript,
+};
+
+/// how often a streaming command checks for Ctrl-C
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// pause between `PING`s in latency mode, like redis-cli
+const LATENCY_INTERVAL: Duration = Duration::from_millis(10);
+
+/// how often the latency line is refreshed
+const LATENCY_REFRESH: Duration = Duration::from_secs(1);
+
+/// commands sent per pipelined batch when stdin is not a terminal
+const PIPELINE_CHUNK: usize = 1000;
+
+/// shown for bad `_history` arguments
+const HISTORY_USAGE: &str = "usage: _history [-t] [n] | -c";
+
+/// shown for bad `_watch` arguments
+const WATCH_USAGE: &str = "usage: _watch [pattern]";
+
+/// shown for bad `_scan` arguments
+const SCAN_USAGE: &str = "usage: _scan [MATCH pattern] [TYPE type]";
+
+/// shown for bad `_browse` arguments
+const BROWSE_USAGE: &str = "usage: _browse [TYPE type]";
+
+/// keys asked for per `SCAN` step by `_scan`, small so Ctrl-C is noticed quickly
+const SCAN_COUNT: usize = 100;
+
+/// slowlog entries shown when `_slowlog` isn't given a count, like `SLOWLOG GET`
+const SLOWLOG_COUNT: usize = 10;
+
+/// exit code for a successful reply
+pub const EXIT_OK: i32 = 0;
+/// exit code for a redis error reply
+pub const EXIT_ERROR_REPLY: i32 = 1;
+/// exit code for a connection or protocol failure
+pub const EXIT_FAILURE: i32 = 2;
+
+/// what the input loop should do after a line was handled
+#[derive(Debug, PartialEq, Eq)]
+pub enum LineAction {
+    Continue,
+    Quit,
+}
+
+/// guards against destructive commands in interactive use, all off in `--test-mode`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Safety {
+    /// ask before FLUSHDB / FLUSHALL
+    pub confirm_flush: bool,
+    /// warn that DEBUG can crash or block the server
+    pub warn_debug: bool,
+}
+
+impl Safety {
+    pub fn new(test_mode: bool) -> Self {
+        Self {
+            confirm_flush: !test_mode,
+            warn_debug: !test_mode,
+        }
+    }
+}
+
+/// command execution shared by the readline loop and the `--simple` loop
+pub struct Session {
+    client: RedisClient,
+    cache: Arc<Mutex<CommandCache>>,
+    transcript: Option<Transcript>,
+    output_options: OutputOptions,
+    safety: Safety,
+    history: Arc<Mutex<History>>,
+    /// follow MOVED and ASK redirects, set with `-c`
+    cluster: Option<Cluster>,
+    /// reject commands with the wrong number of arguments before sending them
+    check_arity: bool,
+}
+
+impl Session {
+    pub fn new(
+        client: RedisClient,
+        cache: Arc<Mutex<CommandCache>>,
+        transcript: Option<Transcript>,
+        output_options: OutputOptions,
+        safety: Safety,
+        history: Arc<Mutex<History>>,
+    ) -> Self {
+        Self {
+            client,
+            cache,
+            transcript,
+            output_options,
+            safety,
+            history,
+            cluster: None,
+            check_arity: true,
+        }
+    }
+
+    /// `-c`: send commands on to the node owning their key
+    pub fn with_cluster(mut self, cluster: bool) -> Self {
+        self.cluster = cluster.then(Cluster::default);
+        self
+    }
+
+    /// `--no-arity-check` turns it off, for when `COMMAND` is stale
+    pub fn with_arity_check(mut self, check_arity: bool) -> Self {
+        self.check_arity = check_arity;
+        self
+    }
+
+    pub fn client(&self) -> &RedisClient {
+        &self.client
+    }
+
+    /// run one command non-interactively, returning the process exit code
+    pub fn run_command(&mut self, command: &str, out: &mut dyn Write) -> i32 {
+        let args: Vec<&str> = command.split_whitespace().collect();
+        if args.first() == Some(&"_scan") {
+            return match self.scan_report(&args[1..], out) {
+                Ok(_) => EXIT_OK,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    EXIT_FAILURE
+                }
+            };
+        }
+        if args.first().is_some_and(|name| name.starts_with('_')) {
+            let output = self.execute_client_command(&args);
+            return match writeln!(out, "{}", output) {
+                Ok(_) => EXIT_OK,
+                Err(_) => EXIT_FAILURE,
+            };
+        }
+
+        let name = args
+            .first()
+            .map(|name| name.to_uppercase())
+            .unwrap_or_default();
+        if let Some(result) = self.run_streaming(command, &name, out) {
+            return match result {
+                Ok(_) => EXIT_OK,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    EXIT_FAILURE
+                }
+            };
+        }
+
+        if let Some(error) = self.arity_error(command) {
+            eprintln!("(error) {}", error);
+            return EXIT_ERROR_REPLY;
+        }
+        let start = Instant::now();
+        let result =
+            tokenizer::split_args(command).and_then(|args| self.execute_one_shot(&args, out));
+        self.print_elapsed(start);
+        let result = match result {
+            Ok(None) => return finish_streamed(out),
+            Ok(Some(response)) => Ok(response),
+            Err(e) => Err(e),
+        };
+        match &result {
+            Ok(response) => {
+                let hint = RenderHint::resolve(command, &self.output_options);
+                let output = output::render_with_hint(response, hint, &self.output_options);
+                if write_reply(response, &output, self.output_options.raw, out).is_err() {
+                    return EXIT_FAILURE;
+                }
+            }
+            Err(e) => eprintln!("Error: {}", e),
+        }
+
+        exit_code(&result)
+    }
+
+    /// `--time` for a one-shot command goes to stderr, keeping stdout parseable
+    fn print_elapsed(&self, start: Instant) {
+        if self.output_options.timing {
+            eprintln!("{}", output::format_elapsed(start.elapsed()));
+        }
+    }
+
+    /// run a command, in cluster mode on the node owning its key
+    fn execute(&mut self, args: &[Vec<u8>]) -> anyhow::Result<RespType> {
+        let Some(mut cluster) = self.cluster.take() else {
+            return self.execute_with_reconnect(|client| client.execute_args(args));
+        };
+
+        let slot = self.key_slot_of(args);
+        let result = self.execute_with_reconnect(|client| cluster.execute(client, args, slot));
+        self.cluster = Some(cluster);
+        result
+    }
+
+    /// `-r`/`-i`: call `run` `repeat` times, forever when negative, pausing `interval`
+    /// in between; Ctrl-C stops the repeating. Returns the worst exit code, a
+    /// connection failure ends it early
+    pub fn run_repeated(
+        &mut self,
+        repeat: i64,
+        interval: Duration,
+        out: &mut dyn Write,
+        mut run: impl FnMut(&mut Session, &mut dyn Write) -> i32,
+    ) -> i32 {
+        if repeat == 1 {
+            return run(self, out);
+        }
+
+        let streaming = interrupt::Streaming::start();
+        let mut code = EXIT_OK;
+        let mut runs = 0;
+        while (repeat < 0 || runs < repeat) && !streaming.interrupted() {
+            if runs > 0 {
+                let start = Instant::now();
+                while start.elapsed() < interval && !streaming.interrupted() {
+                    thread::sleep(POLL_INTERVAL.min(interval - start.elapsed()));
+                }
+                if streaming.interrupted() {
+                    break;
+                }
+            }
+            code = code.max(run(self, out));
+            runs += 1;
+            if code == EXIT_FAILURE {
+                break;
+            }
+        }
+        code
+    }
+
+    /// run a one-shot command; in raw mode a bulk string reply is streamed to `out`
+    /// as it arrives rather than buffered whole, giving `None`
+    fn execute_one_shot(
+        &mut self,
+        args: &[Vec<u8>],
+        out: &mut dyn Write,
+    ) -> anyhow::Result<Option<RespType>> {
+        if self.output_options.raw && self.cluster.is_none() {
+            return self.client.execute_args_streaming(args, out);
+        }
+        self.execute(args).map(Some)
+    }
+
+    /// slot of the command's first key, from `COMMAND` or else its first argument
+    fn key_slot_of(&self, args: &[Vec<u8>]) -> Option<u16> {
+        let name = String::from_utf8_lossy(args.first()?);
+        let first_key = match self.cache.lock().unwrap().get_command(&name) {
+            Some(info) => usize::try_from(info.first_key).ok().filter(|i| *i > 0)?,
+            None => 1,
+        };
+        args.get(first_key).map(|key| cluster::crc16_slot(key))
+    }
+
+    /// run a command, dialing again and retrying once when the connection dropped
+    fn execute_with_reconnect(
+        &mut self,
+        mut execute: impl FnMut(&mut RedisClient) -> anyhow::Result<RespType>,
+    ) -> anyhow::Result<RespType> {
+        match execute(&mut self.client) {
+            Err(e) if redis_client::is_connection_error(&e) => {
+                if self.client.reconnect().is_err() {
+                    return Err(e);
+                }
+                eprintln!("(reconnected)");
+                execute(&mut self.client)
+            }
+            result => result,
+        }
+    }
+
+    /// run one command given as exact argument bytes, returning the process exit code
+    pub fn run_args(&mut self, args: &[Vec<u8>], out: &mut dyn Write) -> i32 {
+        let start = Instant::now();
+        let result = self.execute_one_shot(args, out);
+        self.print_elapsed(start);
+        let result = match result {
+            Ok(None) => return finish_streamed(out),
+            Ok(Some(response)) => Ok(response),
+            Err(e) => Err(e),
+        };
+        match &result {
+            Ok(response) => {
+                let hint = RenderHint::resolve(
+                    &String::from_utf8_lossy(&args[..args.len().min(2)].join(&b' ')),
+                    &self.output_options,
+                );
+                let output = output::render_with_hint(response, hint, &self.output_options);
+                if write_reply(response, &output, self.output_options.raw, out).is_err() {
+                    return EXIT_FAILURE;
+                }
+            }
+            Err(e) => eprintln!("Error: {}", e),
+        }
+
+        exit_code(&result)
+    }
+
+    /// handle one input line typed after `prompt`, writing replies to `out`,
+    /// confirmations are read from `input`
+    pub fn execute_line(
+        &mut self,
+        prompt: &str,
+        line: &str,
+        input: &mut dyn BufRead,
+        out: &mut dyn Write,
+    ) -> io::Result<LineAction> {
+        let command: &str = line.trim();
+        if let Some(transcript) = self.transcript.as_mut() {
+            transcript.record_input(prompt, line);
+        }
+        if command.is_empty() {
+            return Ok(LineAction::Continue);
+        }
+        self.history.lock().unwrap().add(command);
+
+        if is_exit_command(command) {
+            return Ok(LineAction::Quit);
+        }
+
+        let output = match tokenizer::split_redirect(command) {
+            Ok((command, Some(redirect))) => {
+                Some(self.execute_redirected(command, &redirect, input, out)?)
+            }
+            Ok((command, None)) => self.dispatch(command, input, out)?,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                Some(format!("Error: {}", e))
+            }
+        };
+        if let Some(transcript) = self.transcript.as_mut()
+            && let Some(output) = output.filter(|output| !output.is_empty())
+        {
+            transcript.record_output(&output);
+        }
+
+        Ok(LineAction::Continue)
+    }
+
+    /// `command > file`: write what the command prints to `file` instead, without
+    /// colors, `>>` appends; only the byte count is shown
+    fn execute_redirected(
+        &mut self,
+        command: &str,
+        redirect: &tokenizer::Redirect,
+        input: &mut dyn BufRead,
+        out: &mut dyn Write,
+    ) -> io::Result<String> {
+        let mut buffer = Vec::new();
+        self.dispatch(command, input, &mut buffer)?;
+        let text = color::strip(&String::from_utf8_lossy(&buffer));
+
+        let written = fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(redirect.append)
+            .truncate(!redirect.append)
+            .open(&redirect.path)
+            .and_then(|mut file| file.write_all(text.as_bytes()));
+        let output = match written {
+            Ok(_) => format!("(written {} bytes to {})", text.len(), redirect.path),
+            Err(e) => format!("(error) can't write {}: {}", redirect.path, e),
+        };
+        writeln!(out, "{}", output)?;
+        Ok(output)
+    }
+
+    /// run a line without its redirection, returning what to record in the transcript
+    fn dispatch(
+        &mut self,
+        command: &str,
+        input: &mut dyn BufRead,
+        out: &mut dyn Write,
+    ) -> io::Result<Option<String>> {
+        let args: Vec<&str> = command.split_whitespace().collect();
+        if args[0].eq_ignore_ascii_case("help") && args.len() == 1 {
+            help::print_help(out)?;
+            if let Some(groups) = help::groups_help(&self.cache.lock().unwrap()) {
+                writeln!(out, "{}", groups)?;
+            }
+            return Ok(None);
+        }
+
+        // client side commands start with `_` and are never sent to the server,
+        // neither is `help <command>`
+        let output = if args[0].eq_ignore_ascii_case("help") {
+            let output = help::command_help(&self.cache.lock().unwrap(), &args[1..]);
+            writeln!(out, "{}", output)?;
+            output
+        } else if args[0] == "_latency" {
+            self.latency(out)
+                .unwrap_or_else(|e| format!("Error: {}", e))
+        } else if args[0] == "_browse" {
+            match browse_type_filter(&args[1..]) {
+                Some(type_filter) => match BrowseApp::new(&mut self.client, type_filter).run() {
+                    Ok(_) => String::new(),
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        format!("Error: {}", e)
+                    }
+                },
+                None => {
+                    writeln!(out, "{}", BROWSE_USAGE)?;
+                    BROWSE_USAGE.to_string()
+                }
+            }
+        } else if args[0] == "_scan" {
+            self.scan_report(&args[1..], out)
+                .unwrap_or_else(|e| format!("Error: {}", e))
+        } else if args[0] == "_watch" {
+            self.watch(&args[1..], input, out)
+                .unwrap_or_else(|e| format!("Error: {}", e))
+        } else if args[0].starts_with('_') {
+            let output = self.execute_client_command(&args);
+            writeln!(out, "{}", output)?;
+            output
+        } else {
+            self.execute_server_command(command, input, out)?
+        };
+
+        Ok(Some(output))
+    }
+
+    fn execute_client_command(&mut self, args: &[&str]) -> String {
+        match args[0] {
+            "_transcript" => switch_transcript(&mut self.transcript, args.get(1).copied()),
+            "_slot" => offline_slot(args),
+            "_keyslot" => self.key_slot(args),
+            "_sameslot" => same_slot(&args[1..]),
+            "_memory" => self.memory_report(),
+            "_info" => self.info_report(&args[1..]),
+            "_version" => version_report(&self.client.server_info()),
+            "_history" => self.history_report(args),
+            "_slowlog" => self.slowlog_report(args),
+            "_clients" => self.clients_report(args),
+            _ => format!("Unknown client command '{}', type 'help'", args[0]),
+        }
+    }
+
+    /// `_history [-t] [n]` lists the last n commands, `-t` with their run time,
+    /// `_history -c` clears them
+    fn history_report(&mut self, args: &[&str]) -> String {
+        let mut history = self.history.lock().unwrap();
+        let mut args = &args[1..];
+        if args.first() == Some(&"-c") {
+            history.clear();
+            return "History cleared".to_string();
+        }
+        let with_time = args.first() == Some(&"-t");
+        if with_time {
+            args = &args[1..];
+        }
+
+        match args {
+            [] => history::format_history(&history, None, with_time),
+            [count] => match count.parse() {
+                Ok(count) => history::format_history(&history, Some(count), with_time),
+                Err(_) => HISTORY_USAGE.to_string(),
+            },
+            _ => HISTORY_USAGE.to_string(),
+        }
+    }
+
+    fn execute_server_command(
+        &mut self,
+        command: &str,
+        input: &mut dyn BufRead,
+        out: &mut dyn Write,
+    ) -> io::Result<String> {
+        let name = command
+            .split_whitespace()
+            .next()
+            .unwrap_or("")
+            .to_uppercase();
+        if self.safety.confirm_flush
+            && (name == "FLUSHDB" || name == "FLUSHALL")
+            && !confirm(
+                &format!("{} removes all keys, continue? (y/N) ", name),
+                input,
+                out,
+            )?
+        {
+            let output = "(cancelled)".to_string();
+            writeln!(out, "{}", output)?;
+            return Ok(output);
+        }
+        if self.safety.warn_debug && name == "DEBUG" {
+            eprintln!("Warning: DEBUG commands can block or crash the server");
+        }
+        if name == "SELECT"
+            && let Some(output) = self.select_out_of_range(command)
+        {
+            writeln!(out, "{}", output)?;
+            return Ok(output);
+        }
+        if let Some(error) = self.arity_error(command) {
+            let output = format!("(error) {}", error);
+            writeln!(out, "{}", output)?;
+            return Ok(output);
+        }
+        if let Some(result) = self.run_streaming(command, &name, out) {
+            return Ok(match result {
+                Ok(output) => output,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    format!("Error: {}", e)
+                }
+            });
+        }
+
+        // 执行命令
+        let start = Instant::now();
+        let result = tokenizer::split_args(command).and_then(|args| self.execute(&args));
+        let elapsed = start.elapsed();
+        let output = match result {
+            Ok(response) => {
+                let hint = RenderHint::resolve(command, &self.output_options);
+                let mut output = output::render_with_hint(&response, hint, &self.output_options);
+                if response.is_err_type()
+                    && let Some(suggestion) = self.suggest_command(command, &response.to_string())
+                {
+                    output = format!("{}\n{}", output, suggestion);
+                }
+                if self.output_options.with_ttl
+                    && !response.is_err_type()
+                    && let Some(ttl) = self.ttl_suffix(command)
+                {
+                    output = format!("{}\n{}", output.trim_end_matches('\n'), ttl);
+                }
+                if self.output_options.verbose {
+                    output = format!(
+                        "{}\n{}",
+                        output.trim_end_matches('\n'),
+                        output::format_reply_size(&response, self.client.last_reply_bytes())
+                    );
+                }
+                if self.output_options.timing {
+                    output = format!(
+                        "{}\n{}",
+                        output.trim_end_matches('\n'),
+                        output::format_elapsed(elapsed)
+                    );
+                }
+                writeln!(out, "{}", output)?;
+                output
+            }
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                format!("Error: {}", e)
+            }
+        };
+
+        Ok(output)
+    }
+
+    /// commands that keep sending after their reply run until Ctrl-C,
+    /// `None` for any other command
+    fn run_streaming(
+        &mut self,
+        command: &str,
+        name: &str,
+        out: &mut dyn Write,
+    ) -> Option<anyhow::Result<String>> {
+        match name {
+            "MONITOR" => Some(self.monitor(out)),
+            _ => unsubscribe_command(name)
+                .map(|unsubscribe| self.subscribe(command, unsubscribe, out, format_message)),
+        }
+    }
+
+    /// print pub/sub messages as they arrive until Ctrl-C, then unsubscribe
+    /// and go back to the prompt
+    fn subscribe(
+        &mut self,
+        command: &str,
+        unsubscribe: &str,
+        out: &mut dyn Write,
+        format: fn(&RespType) -> String,
+    ) -> anyhow::Result<String> {
+        let streaming = interrupt::Streaming::start();
+        self.client.send_command(command)?;
+        writeln!(out, "Reading messages... (press Ctrl-C to quit)")?;
+        self.read_stream(&streaming, out, format)?;
+
+        // drain the unsubscribe confirmations, the last one counts 0 subscriptions
+        self.client.send_command(unsubscribe)?;
+        while let Some(reply) = self.client.poll_resp(Duration::from_secs(1))? {
+            if subscription_count(&reply) == Some(0) {
+                break;
+            }
+        }
+
+        // already recorded frame by frame
+        Ok(String::new())
+    }
+
+    /// `_scan [MATCH pattern] [TYPE type]`: every matching key, printed batch by batch
+    /// as the cursor advances, then a count; Ctrl-C stops it and shows the cursor reached
+    fn scan_report(&mut self, args: &[&str], out: &mut dyn Write) -> anyhow::Result<String> {
+        let mut filter = ScanFilter::default();
+        for option in args.chunks(2) {
+            match option {
+                [name, pattern] if name.eq_ignore_ascii_case("MATCH") => {
+                    filter.pattern = Some(pattern.as_bytes().to_vec())
+                }
+                [name, key_type] if name.eq_ignore_ascii_case("TYPE") => {
+                    filter.key_type = Some(key_type.as_bytes().to_vec())
+                }
+                _ => {
+                    writeln!(out, "{}", SCAN_USAGE)?;
+                    return Ok(SCAN_USAGE.to_string());
+                }
+            }
+        }
+
+        let streaming = interrupt::Streaming::start();
+        let mut found = 0;
+        let end = self.client.scan_keys(SCAN_COUNT, &filter, |_, keys| {
+            for key in keys {
+                found += 1;
+                writeln!(out, "{}) {}", found, output::quote(&key))?;
+            }
+            out.flush()?;
+            Ok(!streaming.interrupted())
+        })?;
+
+        let summary = match end {
+            ScanEnd::Complete => format!("({} keys)", found),
+            ScanEnd::Stopped { cursor } => format!(
+                "(stopped after {} keys, at cursor {})",
+                found,
+                String::from_utf8_lossy(&cursor)
+            ),
+            ScanEnd::Failed => "(error) SCAN failed".to_string(),
+        };
+        writeln!(out, "{}", summary)?;
+        Ok(summary)
+    }
+
+    /// `_watch [pattern]`: print the current database's key events as `<event> <key>`
+    /// until Ctrl-C, offering to turn keyspace notifications on when they're off
+    fn watch(
+        &mut self,
+        args: &[&str],
+        input: &mut dyn BufRead,
+        out: &mut dyn Write,
+    ) -> anyhow::Result<String> {
+        let pattern = match args {
+            [] => format!("__keyevent@{}__:*", self.client.address().db().unwrap_or(0)),
+            [pattern] => pattern.to_string(),
+            _ => {
+                writeln!(out, "{}", WATCH_USAGE)?;
+                return Ok(WATCH_USAGE.to_string());
+            }
+        };
+
+        // a server refusing CONFIG, like a managed one, may still send events
+        let reply = self
+            .client
+            .execute(&[b"CONFIG", b"GET", b"notify-keyspace-events"])?;
+        if config_value(&reply).is_some_and(|flags| flags.is_empty()) {
+            eprintln!(
+                "Warning: keyspace notifications are disabled (notify-keyspace-events is empty)"
+            );
+            if confirm(
+                "Enable them with CONFIG SET notify-keyspace-events KEA? (y/N) ",
+                input,
+                out,
+            )? {
+                let reply =
+                    self.client
+                        .execute(&[b"CONFIG", b"SET", b"notify-keyspace-events", b"KEA"])?;
+                if reply.is_err_type() {
+                    eprintln!("(error) {}", reply);
+                }
+            }
+        }
+
+        self.subscribe(
+            &format!("PSUBSCRIBE {}", tokenizer::quote_arg(&pattern)),
+            "PUNSUBSCRIBE",
+            out,
+            format_event,
+        )
+    }
+
+    /// print every command the server processes until Ctrl-C; a monitoring
+    /// connection can't go back to normal, so it's dialed again afterwards
+    fn monitor(&mut self, out: &mut dyn Write) -> anyhow::Result<String> {
+        let streaming = interrupt::Streaming::start();
+        self.client.send_command("MONITOR")?;
+        self.read_stream(&streaming, out, RespType::to_string)?;
+        self.client.reconnect()?;
+
+        Ok(String::new())
+    }
+
+    /// `--bigkeys`: report the biggest key of each type, returning the process exit code
+    pub fn run_bigkeys(&mut self, out: &mut dyn Write) -> i32 {
+        match bigkeys::run(&mut self.client, out) {
+            Ok(_) => EXIT_OK,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                EXIT_FAILURE
+            }
+        }
+    }
+
+    /// `--latency`: sample round trips until Ctrl-C, returning the process exit code
+    pub fn run_latency(&mut self, out: &mut dyn Write) -> i32 {
+        match self.latency(out) {
+            Ok(_) => EXIT_OK,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                EXIT_FAILURE
+            }
+        }
+    }
+
+    /// time `PING` round trips until Ctrl-C, rewriting one line with min/max/avg
+    /// once per second; returns the final line
+    fn latency(&mut self, out: &mut dyn Write) -> anyhow::Result<String> {
+        let streaming = interrupt::Streaming::start();
+        let mut stats = LatencyStats::default();
+        let mut last_refresh = Instant::now();
+
+        while !streaming.interrupted() {
+            let start = Instant::now();
+            let reply = self.client.execute_args(&[b"PING".to_vec()])?;
+            if reply.is_err_type() {
+                anyhow::bail!("{}", reply);
+            }
+            stats.add(start.elapsed());
+
+            if last_refresh.elapsed() >= LATENCY_REFRESH {
+                write!(out, "\r{}", stats)?;
+                out.flush()?;
+                last_refresh = Instant::now();
+            }
+            thread::sleep(LATENCY_INTERVAL);
+        }
+
+        let line = stats.to_string();
+        writeln!(out, "\r{}", line)?;
+        Ok(line)
+    }
+
+    /// print each frame as it arrives until Ctrl-C, recording it to the transcript
+    /// as it goes so a long stream isn't held in memory
+    fn read_stream(
+        &mut self,
+        streaming: &interrupt::Streaming,
+        out: &mut dyn Write,
+        format: fn(&RespType) -> String,
+    ) -> anyhow::Result<()> {
+        while !streaming.interrupted() {
+            if let Some(frame) = self.client.poll_resp(POLL_INTERVAL)? {
+                let text = format(&frame);
+                writeln!(out, "{}", text)?;
+                out.flush()?;
+                if let Some(transcript) = self.transcript.as_mut() {
+                    transcript.record_output(&text);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `(ttl: 300s)` for a command reading a single key, `None` for other commands
+    fn ttl_suffix(&mut self, command: &str) -> Option<String> {
+        let key = ttl_key(&self.cache.lock().unwrap(), command)?;
+        let ttl = match self.client.execute(&[b"TTL", &key]) {
+            Ok(RespType::Integers(i)) => i.value(),
+            _ => return None,
+        };
+
+        Some(match ttl {
+            -2 => "(ttl: key does not exist)".to_string(),
+            -1 => "(ttl: no expiry)".to_string(),
+            seconds => format!("(ttl: {}s)", seconds),
+        })
+    }
+
+    /// `_memory`: MEMORY DOCTOR advice followed by MEMORY STATS as a table
+    fn memory_report(&mut self) -> String {
+        let doctor = match self.client.execute(&[b"MEMORY", b"DOCTOR"]) {
+            Ok(advice) => advice.to_string(),
+            Err(e) => format!("Error: {}", e),
+        };
+        let stats = match self.client.execute(&[b"MEMORY", b"STATS"]) {
+            Ok(stats) if stats.is_err_type() => stats.to_string(),
+            Ok(stats) => output::format_table(&memory::stats_rows(&stats)),
+            Err(e) => format!("Error: {}", e),
+        };
+
+        format!("# Doctor\n{}\n\n# Stats\n{}", doctor, stats)
+    }
+
+    /// `_info [section ...]`: `INFO` as one aligned table per section
+    fn info_report(&mut self, sections: &[&str]) -> String {
+        let command: Vec<&[u8]> = std::iter::once(b"INFO".as_slice())
+            .chain(sections.iter().map(|section| section.as_bytes()))
+            .collect();
+        match self.client.execute(&command) {
+            Ok(reply) if reply.is_err_type() => reply.to_string(),
+            Ok(reply) => {
+                let sections = info::parse_info(&reply.to_string());
+                if sections.is_empty() {
+                    return "(empty section)".to_string();
+                }
+                info::format_sections(&sections)
+            }
+            Err(e) => format!("Error: {}", e),
+        }
+    }
+
+    /// `db0: 1523 keys, used_memory 42.10M` from `DBSIZE` and `INFO memory`, leaving out
+    /// what the server refuses, like a user without the permission; `None` if it refuses both
+    pub fn db_summary(&mut self) -> Option<String> {
+        let keys = match self.client.execute(&[b"DBSIZE"]) {
+            Ok(RespType::Integers(count)) => Some(format!("{} keys", count.value())),
+            _ => None,
+        };
+        let used_memory = match self.client.execute(&[b"INFO", b"memory"]) {
+            Ok(reply) if !reply.is_err_type() => info::parse_info(&reply.to_string())
+                .get("Memory")
+                .and_then(|memory| memory.get("used_memory"))
+                .and_then(|bytes| bytes.parse().ok())
+                .map(|bytes| format!("used_memory {}", output::format_bytes(bytes))),
+            _ => None,
+        };
+
+        let parts: Vec<String> = keys.into_iter().chain(used_memory).collect();
+        if parts.is_empty() {
+            return None;
+        }
+        Some(format!(
+            "db{}: {}",
+            self.client.address().db().unwrap_or(0),
+            parts.join(", ")
+        ))
+    }
+
+    /// `_slowlog [count]`: the last `SLOWLOG GET` entries as a table, slowest first
+    fn slowlog_report(&mut self, args: &[&str]) -> String {
+        let count = match args {
+            [_] => SLOWLOG_COUNT,
+            [_, count] => match count.parse::<usize>() {
+                Ok(count) => count,
+                Err(_) => return "usage: _slowlog [count]".to_string(),
+            },
+            _ => return "usage: _slowlog [count]".to_string(),
+        };
+
+        match self.client.execute_args(&[
+            b"SLOWLOG".to_vec(),
+            b"GET".to_vec(),
+            count.to_string().into_bytes(),
+        ]) {
+            Ok(reply) if reply.is_err_type() => reply.to_string(),
+            Ok(reply) => slowlog::format_entries(&slowlog::parse_entries(&reply)),
+            Err(e) => format!("Error: {}", e),
+        }
+    }
+
+    /// `_clients [--by id|idle|age]`: `CLIENT LIST` as a table
+    fn clients_report(&mut self, args: &[&str]) -> String {
+        let sort_by = match args {
+            [_] => Some(SortBy::Id),
+            [_, "--by", by] => SortBy::from_name(by),
+            _ => None,
+        };
+        let Some(sort_by) = sort_by else {
+            return "usage: _clients [--by id|idle|age]".to_string();
+        };
+
+        match self.client.execute(&[b"CLIENT", b"LIST"]) {
+            Ok(reply) if reply.is_err_type() => reply.to_string(),
+            Ok(reply) => {
+                clients::format_clients(&clients::parse_client_list(&reply.to_string()), sort_by)
+            }
+            Err(e) => format!("Error: {}", e),
+        }
+    }
+
+    /// `_keyslot <key>`: slot computed locally, confirmed by `CLUSTER KEYSLOT` in cluster mode
+    fn key_slot(&mut self, args: &[&str]) -> String {
+        let [_, key] = args else {
+            return "usage: _keyslot <key>".to_string();
+        };
+
+        let slot = cluster::crc16_slot(key.as_bytes());
+        match self
+            .client
+            .execute(&[b"CLUSTER", b"KEYSLOT", key.as_bytes()])
+        {
+            Ok(RespType::Integers(i)) if i.value() == slot as isize => {
+                format!("{} (confirmed by CLUSTER KEYSLOT)", slot)
+            }
+            Ok(RespType::Integers(i)) => format!("{} (server says {})", slot, i.value()),
+            // cluster support disabled or not permitted
+            _ => slot.to_string(),
+        }
+    }
+
+    /// `SELECT` past the server's last database, caught before it is sent
+    fn select_out_of_range(&self, command: &str) -> Option<String> {
+        let databases = self.client.databases().filter(|count| *count > 0)?;
+        let db: u32 = command.split_whitespace().nth(1)?.parse().ok()?;
+        (db >= databases).then(|| format!("(error) db index out of range (0-{})", databases - 1))
+    }
+
+    /// checked locally against the cached `COMMAND` arity, saving the round trip
+    fn arity_error(&self, command: &str) -> Option<String> {
+        if !self.check_arity {
+            return None;
+        }
+        let args = tokenizer::split_args(command).ok()?;
+        self.cache.lock().unwrap().arity_error(&args)
+    }
+
+    /// "Did you mean GET?" for an `unknown command` error, if a close command is known
+    fn suggest_command(&self, command: &str, error: &str) -> Option<String> {
+        if !error.starts_with("ERR unknown command") {
+            return None;
+        }
+
+        let name = command.split_whitespace().next()?;
+        let closest = self.cache.lock().unwrap().closest_command(name)?;
+        Some(format!("Did you mean {}?", closest))
+    }
+
+    /// pipeline lines from a non-interactive input in batches, printing replies
+    /// as each batch completes; returns the worst exit code
+    pub fn run_pipe(&mut self, input: &mut dyn BufRead, out: &mut dyn Write) -> i32 {
+        let mut code = EXIT_OK;
+        let mut lines = input.lines();
+
+        loop {
+            let (batch, commands) = match read_batch(&mut lines, PIPELINE_CHUNK, &mut code) {
+                Ok(batch) => batch,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    return EXIT_FAILURE;
+                }
+            };
+            if batch.is_empty() {
+                return code;
+            }
+
+            let replies = match self.client.pipeline(commands) {
+                Ok(replies) => replies,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    return EXIT_FAILURE;
+                }
+            };
+            code = code.max(self.write_replies(&batch, replies, out));
+        }
+    }
+
+    /// `--multi`: all piped commands in one `MULTI`/`EXEC` transaction
+    pub fn run_transaction(&mut self, input: &mut dyn BufRead, out: &mut dyn Write) -> i32 {
+        let mut code = EXIT_OK;
+        let (batch, commands) = match read_batch(&mut input.lines(), usize::MAX, &mut code) {
+            Ok(batch) => batch,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                return EXIT_FAILURE;
+            }
+        };
+        if batch.is_empty() {
+            return code;
+        }
+
+        match self.client.transaction(commands) {
+            Ok(replies) => code.max(self.write_replies(&batch, replies, out)),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                EXIT_ERROR_REPLY
+            }
+        }
+    }
+
+    /// print the replies of piped commands, returning the worst exit code among them
+    fn write_replies(&self, batch: &[String], replies: Vec<RespType>, out: &mut dyn Write) -> i32 {
+        let mut code = EXIT_OK;
+        for (line, reply) in batch.iter().zip(replies) {
+            let hint = RenderHint::resolve(line, &self.output_options);
+            let output = output::render_with_hint(&reply, hint, &self.output_options);
+            if writeln!(out, "{}", output).is_err() {
+                return EXIT_FAILURE;
+            }
+            code = code.max(exit_code(&Ok(reply)));
+        }
+        code
+    }
+
+    /// `--simple`: minimal REPL without line editing, completion or hints, until EOF
+    /// or an exit command
+    pub fn run_simple(&mut self, input: &mut dyn BufRead, out: &mut dyn Write) -> io::Result<()> {
+        let mut line = String::new();
+        loop {
+            let prompt = self.client.prompt();
+            write!(out, "{}", prompt)?;
+            out.flush()?;
+
+            line.clear();
+            if input.read_line(&mut line)? == 0 {
+                break;
+            }
+
+            if self.execute_line(&prompt, &line, input, out)? == LineAction::Quit {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// the command leaving the subscribed mode a subscribe command enters
+fn unsubscribe_command(name: &str) -> Option<&'static str> {
+    match name {
+        "SUBSCRIBE" => Some("UNSUBSCRIBE"),
+        "PSUBSCRIBE" => Some("PUNSUBSCRIBE"),
+        "SSUBSCRIBE" => Some("SUNSUBSCRIBE"),
+        _ => None,
+    }
+}
+
+/// `1) "message"` `2) "channel"` `3) "payload"`, one element per line
+fn format_message(message: &RespType) -> String {
+    let elements = match message {
+        RespType::Pushes(push) => push.elements(),
+        RespType::Arrays(array) => array.as_slice(),
+        other => return other.to_string(),
+    };
+
+    elements
+        .iter()
+        .enumerate()
+        .map(|(i, element)| match element {
+            RespType::BulkStrings(_) | RespType::SimpleStrings(_) => {
+                format!("{}) \"{}\"", i + 1, element)
+            }
+            RespType::Integers(n) => format!("{}) (integer) {}", i + 1, n.value()),
+            _ => format!("{}) {}", i + 1, element),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `set mykey` for a `__keyevent@0__:set` message carrying the key, or a
+/// `__keyspace@0__:mykey` one carrying the event; other frames as `format_message`
+fn format_event(message: &RespType) -> String {
+    let elements = match message {
+        RespType::Pushes(push) => push.elements(),
+        RespType::Arrays(array) => array.as_slice(),
+        other => return other.to_string(),
+    };
+    let [kind, _, channel, payload] = elements else {
+        return format_message(message);
+    };
+    if kind.to_string() != "pmessage" {
+        return format_message(message);
+    }
+
+    let channel = channel.to_string();
+    match channel.split_once("__:") {
+        Some((prefix, key)) if prefix.starts_with("__keyspace@") => format!("{} {}", payload, key),
+        Some((_, event)) => format!("{} {}", event, payload),
+        None => format!("{} {}", channel, payload),
+    }
+}
+
+/// the value of a one parameter `CONFIG GET` reply, a map in RESP3 or a pair in RESP2
+fn config_value(reply: &RespType) -> Option<String> {
+    match reply {
+        RespType::Maps(map) => map.iter().next().map(|(_, value)| value.to_string()),
+        RespType::Arrays(array) => array.get(1).map(RespType::to_string),
+        _ => None,
+    }
+}
+
+/// the subscription count ending an (un)subscribe confirmation
+fn subscription_count(reply: &RespType) -> Option<isize> {
+    let elements = match reply {
+        RespType::Pushes(push) => push.elements(),
+        RespType::Arrays(array) => array.as_slice(),
+        _ => return None,
+    };
+    match elements.get(2) {
+        Some(RespType::Integers(n)) => Some(n.value()),
+        _ => None,
+    }
+}
+
+/// ask a yes/no question, only `y` / `yes` confirm
+fn confirm(question: &str, input: &mut dyn BufRead, out: &mut dyn Write) -> io::Result<bool> {
+    write!(out, "{}", question)?;
+    out.flush()?;
+
+    let mut answer = String::new();
+    input.read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// a line ending the session: `quit`, `exit`, `\q` or `:q`, in any case
+fn is_exit_command(command: &str) -> bool {
+    ["quit", "exit", "\\q", ":q"]
+        .iter()
+        .any(|alias| command.eq_ignore_ascii_case(alias))
+}
+
+/// the key whose TTL should be shown after `command`, if it reads exactly one key
+fn ttl_key(cache: &CommandCache, command: &str) -> Option<Vec<u8>> {
+    let mut args = tokenizer::split_args(command).ok()?.into_iter();
+    let name = args.next()?;
+    if !cache.is_single_key_read(&String::from_utf8_lossy(&name)) {
+        return None;
+    }
+    args.next()
+}
+
+/// client version, plus the server version and mode from HELLO
+fn version_report(server_info: &ServerInfo) -> String {
+    let mut text = format!("client: rredis-cli {}", env!("CARGO_PKG_VERSION"));
+    if server_info.version.is_empty() {
+        text.push_str("\nserver: unknown");
+    } else {
+        let mut details = vec![];
+        if !server_info.mode.is_empty() {
+            details.push(format!("{} mode", server_info.mode));
+        }
+        if !server_info.role.is_empty() {
+            details.push(server_info.role.clone());
+        }
+        if !server_info.proto.is_empty() {
+            details.push(format!("RESP{}", server_info.proto));
+        }
+        text.push_str(&format!(
+            "\nserver: {} {} ({})",
+            server_info.server,
+            server_info.version,
+            details.join(", ")
+        ));
+    }
+    text
+}
+
+/// up to `limit` piped commands with their lines, blank lines and `#` comments are
+/// skipped and lines that don't parse only raise `code`
+fn read_batch(
+    lines: &mut dyn Iterator<Item = io::Result<String>>,
+    limit: usize,
+    code: &mut i32,
+) -> io::Result<(Vec<String>, Vec<RespType>)> {
+    let mut batch = Vec::new();
+    let mut commands = Vec::new();
+    for line in lines {
+        let line = line?.trim().to_string();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match tokenizer::split_args(&line) {
+            Ok(args) => {
+                commands.push(RespType::from_args(args));
+                batch.push(line);
+            }
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                *code = (*code).max(EXIT_ERROR_REPLY);
+            }
+        }
+        if batch.len() == limit {
+            break;
+        }
+    }
+    Ok((batch, commands))
+}
+
+/// the type to browse from `_browse [TYPE type]`, `None` for other arguments
+fn browse_type_filter(args: &[&str]) -> Option<Option<String>> {
+    match args {
+        [] => Some(None),
+        [name, key_type] if name.eq_ignore_ascii_case("TYPE") => {
+            Some(Some(key_type.to_lowercase()))
+        }
+        _ => None,
+    }
+}
+
+/// `_slot <key>`: the cluster slot computed locally, without asking the server
+fn offline_slot(args: &[&str]) -> String {
+    match args {
+        [_, key] => cluster::crc16_slot(key.as_bytes()).to_string(),
+        _ => "usage: _slot <key>".to_string(),
+    }
+}
+
+/// `_sameslot k1 k2 ...`: whether keys hash to one slot, as multi-key commands need in a cluster
+fn same_slot(keys: &[&str]) -> String {
+    if keys.is_empty() {
+        return "usage: _sameslot <key> [key ...]".to_string();
+    }
+
+    let slots: Vec<u16> = keys
+        .iter()
+        .map(|key| cluster::crc16_slot(key.as_bytes()))
+        .collect();
+    if slots.iter().all(|&slot| slot == slots[0]) {
+        return format!("all {} keys map to slot {}", keys.len(), slots[0]);
+    }
+
+    let mut text = String::from("keys map to different slots:");
+    for (key, slot) in keys.iter().zip(slots) {
+        text.push_str(&format!("\n  {} -> {}", key, slot));
+    }
+    text
+}
+
+/// `_transcript <file>` starts recording the session, `_transcript off` stops it
+fn switch_transcript(transcript: &mut Option<Transcript>, arg: Option<&str>) -> String {
+    match arg {
+        None => match transcript {
+            Some(t) => format!("Recording transcript to {}", t.path()),
+            None => "Transcript is off, usage: _transcript <file>|off".to_string(),
+        },
+        Some("off") => match transcript.take() {
+            Some(t) => format!("Transcript saved to {}", t.path()),
+            None => "Transcript is off".to_string(),
+        },
+        Some(path) => match Transcript::open(path) {
+            Ok(t) => {
+                *transcript = Some(t);
+                format!("Recording transcript to {}", path)
+            }
+            Err(e) => format!("Error: could not open {}: {}", path, e),
+        },
+    }
+}
+
+/// a one-shot reply on stdout, error replies on stderr so scripts can tell them from data;
+/// in raw mode a bulk string is written byte for byte rather than as lossy UTF-8
+fn write_reply(
+    response: &RespType,
+    output: &str,
+    raw: bool,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    match response {
+        _ if response.is_err_type() => {
+            eprintln!("{}", output);
+            Ok(())
+        }
+        RespType::BulkStrings(bs) if raw => {
+            out.write_all(bs.value())?;
+            writeln!(out)
+        }
+        _ => writeln!(out, "{}", output),
+    }
+}
+
+/// end a streamed bulk reply with the newline `write_reply` would add
+fn finish_streamed(out: &mut dyn Write) -> i32 {
+    match writeln!(out) {
+        Ok(_) => EXIT_OK,
+        Err(_) => EXIT_FAILURE,
+    }
+}
+
+/// map a command outcome to the process exit code
+fn exit_code(result: &anyhow::Result<RespType>) -> i32 {
+    match result {
+        Ok(RespType::Unknown) => EXIT_FAILURE,
+        Ok(response) if response.is_err_type() => EXIT_ERROR_REPLY,
+        Ok(_) => EXIT_OK,
+        Err(_) => EXIT_FAILURE,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        byte_buffer::BytesBuffer,
+        command_cache::tests::{cache, command},
+        redis_client::tests::{HANDSHAKE, serve},
+    };
+
+    fn decode(bytes: &[u8]) -> RespType {
+        let mut buff = BytesBuffer::new(64);
+        buff.put_u8_slice(bytes);
+        RespType::decode(&mut buff)
+    }
+
+    /// a session on a loopback server answering with `replies` after the handshake
+    fn session(replies: &[&'static [u8]]) -> (Session, std::sync::mpsc::Receiver<Vec<u8>>) {
+        let (address, requests) = serve([&HANDSHAKE[..], replies].concat());
+        let client = RedisClient::connect(address).unwrap();
+        let session = Session::new(
+            client,
+            Arc::new(Mutex::new(CommandCache::new())),
+            None,
+            OutputOptions::default(),
+            Safety::new(true),
+            Arc::new(Mutex::new(History::new(10))),
+        );
+        (session, requests)
+    }
+
+    #[test]
+    fn exit_codes_follow_the_reply() {
+        assert_eq!(exit_code(&Ok(decode(b"+OK\r\n"))), EXIT_OK);
+        assert_eq!(exit_code(&Ok(decode(b":0\r\n"))), EXIT_OK);
+        assert_eq!(exit_code(&Ok(decode(b"-ERR no\r\n"))), EXIT_ERROR_REPLY);
+        assert_eq!(exit_code(&Ok(RespType::Unknown)), EXIT_FAILURE);
+        assert_eq!(exit_code(&Err(anyhow::anyhow!("closed"))), EXIT_FAILURE);
+    }
+
+    #[test]
+    fn exit_aliases_in_any_case() {
+        for alias in ["quit", "EXIT", "\\q", ":Q"] {
+            assert!(is_exit_command(alias), "{}", alias);
+        }
+        assert!(!is_exit_command("quit now"));
+        assert!(!is_exit_command("q"));
+    }
+
+    #[test]
+    fn test_mode_turns_the_guards_off() {
+        assert_eq!(
+            Safety::new(true),
+            Safety {
+                confirm_flush: false,
+                warn_debug: false
+            }
+        );
+        assert!(Safety::new(false).confirm_flush && Safety::new(false).warn_debug);
+    }
+
+    #[test]
+    fn version_report_with_and_without_hello() {
+        let client = format!("client: rredis-cli {}", env!("CARGO_PKG_VERSION"));
+        assert_eq!(
+            version_report(&ServerInfo::default()),
+            format!("{}\nserver: unknown", client)
+        );
+
+        let info = ServerInfo {
+            server: "redis".to_string(),
+            version: "7.2.4".to_string(),
+            proto: "3".to_string(),
+            mode: "standalone".to_string(),
+            role: "master".to_string(),
+        };
+        assert_eq!(
+            version_report(&info),
+            format!(
+                "{}\nserver: redis 7.2.4 (standalone mode, master, RESP3)",
+                client
+            )
+        );
+    }
+
+    #[test]
+    fn ttl_only_after_single_key_reads() {
+        let cache = cache(vec![
+            command("GET", 2, &["readonly", "fast"], 1),
+            command("SET", -3, &["write"], 1),
+        ]);
+        assert_eq!(ttl_key(&cache, "get k"), Some(b"k".to_vec()));
+        assert_eq!(ttl_key(&cache, "GET \"a key\""), Some(b"a key".to_vec()));
+        assert_eq!(ttl_key(&cache, "SET k v"), None);
+        assert_eq!(ttl_key(&cache, "GET"), None);
+        assert_eq!(ttl_key(&cache, "UNKNOWN k"), None);
+    }
+
+    #[test]
+    fn slots_are_computed_offline() {
+        assert_eq!(offline_slot(&["_slot", "foo"]), "12182");
+        assert_eq!(offline_slot(&["_slot"]), "usage: _slot <key>");
+
+        assert_eq!(
+            same_slot(&["{user1000}.following", "{user1000}.followers"]),
+            "all 2 keys map to slot 3443"
+        );
+        assert_eq!(
+            same_slot(&["foo", "bar"]),
+            "keys map to different slots:\n  foo -> 12182\n  bar -> 5061"
+        );
+        assert_eq!(same_slot(&[]), "usage: _sameslot <key> [key ...]");
+    }
+
+    #[test]
+    fn browse_takes_an_optional_type() {
+        assert_eq!(browse_type_filter(&[]), Some(None));
+        assert_eq!(
+            browse_type_filter(&["type", "HASH"]),
+            Some(Some("hash".to_string()))
+        );
+        assert_eq!(browse_type_filter(&["hash"]), None);
+        assert_eq!(browse_type_filter(&["MATCH", "a*"]), None);
+    }
+
+    #[test]
+    fn batches_skip_comments_and_bad_lines() {
+        let text = "SET a 1\n\n# a comment\nGET \"a\nGET a\nGET b\nGET c\n";
+        let mut lines = text.lines().map(|line| Ok(line.to_string()));
+        let mut code = EXIT_OK;
+
+        let (batch, commands) = read_batch(&mut lines, 3, &mut code).unwrap();
+        assert_eq!(batch, ["SET a 1", "GET a", "GET b"]);
+        assert_eq!(commands.len(), 3);
+        assert_eq!(code, EXIT_ERROR_REPLY);
+
+        let (batch, _) = read_batch(&mut lines, 3, &mut code).unwrap();
+        assert_eq!(batch, ["GET c"]);
+        let (batch, _) = read_batch(&mut lines, 3, &mut code).unwrap();
+        assert!(batch.is_empty());
+    }
+
+    #[test]
+    fn transcript_switches_on_and_off() {
+        let path = std::env::temp_dir().join(format!(
+            "rredis_cli_session_transcript_{}",
+            std::process::id()
+        ));
+        let path = path.to_str().unwrap();
+        let mut transcript = None;
+
+        assert_eq!(
+            switch_transcript(&mut transcript, None),
+            "Transcript is off, usage: _transcript <file>|off"
+        );
+        assert_eq!(
+            switch_transcript(&mut transcript, Some(path)),
+            format!("Recording transcript to {}", path)
+        );
+        assert!(transcript.is_some());
+        assert_eq!(
+            switch_transcript(&mut transcript, Some("off")),
+            format!("Transcript saved to {}", path)
+        );
+        assert!(transcript.is_none());
+        assert_eq!(
+            switch_transcript(&mut transcript, Some("off")),
+            "Transcript is off"
+        );
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn simple_mode_reads_commands_until_quit() {
+        let (mut session, requests) = session(&[b"+PONG\r\n", b"$1\r\nv\r\n"]);
+        let mut input = io::Cursor::new("PING\n\nGET k\nquit\nPING\n");
+        let mut out = Vec::new();
+        session.run_simple(&mut input, &mut out).unwrap();
+
+        let prompt = session.client().prompt();
+        let text = color::strip(&String::from_utf8(out).unwrap());
+        assert_eq!(text, format!("{0}PONG\n{0}{0}\"v\"\n{0}", prompt));
+        let sent: Vec<Vec<u8>> = requests.try_iter().skip(HANDSHAKE.len()).collect();
+        assert_eq!(
+            sent,
+            [
+                b"*1\r\n$4\r\nPING\r\n".to_vec(),
+                b"*2\r\n$3\r\nGET\r\n$1\r\nk\r\n".to_vec()
+            ]
+        );
+    }
+
+    #[test]
+    fn repeated_commands_run_the_given_count() {
+        let (mut session, requests) =
+            session(&[b"+PONG\r\n", b"+PONG\r\n", b"+PONG\r\n", b"+PONG\r\n"]);
+        let mut out = Vec::new();
+        let code = session.run_repeated(3, Duration::ZERO, &mut out, |session, out| {
+            session.run_command("PING", out)
+        });
+
+        assert_eq!(code, EXIT_OK);
+        assert_eq!(
+            color::strip(&String::from_utf8(out).unwrap()),
+            "PONG\nPONG\nPONG\n"
+        );
+        assert_eq!(requests.try_iter().skip(HANDSHAKE.len()).count(), 3);
+    }
+}
@@ -0,0 +1,163 @@
+use std::time::{Duration, UNIX_EPOCH};
+
+use chrono::{DateTime, Local};
+
+use crate::{output, redis_type::RespType};
+
+/// one `SLOWLOG GET` entry
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlowlogEntry {
+    pub id: i64,
+    /// unix time the command was run at
+    pub timestamp: i64,
+    pub micros: i64,
+    pub args: Vec<String>,
+    /// client address and name, missing before redis 4.0
+    pub client_addr: String,
+    pub client_name: String,
+}
+
+/// the entries of a `SLOWLOG GET` reply, nested arrays in both RESP2 and RESP3,
+/// only their length differs by server version; malformed entries are skipped
+pub fn parse_entries(reply: &RespType) -> Vec<SlowlogEntry> {
+    elements(reply)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(parse_entry)
+        .collect()
+}
+
+/// `[id, timestamp, micros, [args], addr, name]`, the last two optional
+fn parse_entry(entry: &RespType) -> Option<SlowlogEntry> {
+    let fields = elements(entry)?;
+    let integer = |index: usize| match fields.get(index) {
+        Some(RespType::Integers(i)) => Some(i.value() as i64),
+        _ => None,
+    };
+    let text = |index: usize| {
+        fields
+            .get(index)
+            .map(RespType::to_string)
+            .unwrap_or_default()
+    };
+
+    Some(SlowlogEntry {
+        id: integer(0)?,
+        timestamp: integer(1)?,
+        micros: integer(2)?,
+        args: elements(fields.get(3)?)?
+            .iter()
+            .map(|arg| match arg {
+                RespType::BulkStrings(bs) => output::quote(bs.value()),
+                other => other.to_string(),
+            })
+            .collect(),
+        client_addr: text(4),
+        client_name: text(5),
+    })
+}
+
+fn elements(resp: &RespType) -> Option<&[RespType]> {
+    match resp {
+        RespType::Arrays(array) => Some(array.as_slice()),
+        _ => None,
+    }
+}
+
+/// aligned table, slowest first, times local and durations in milliseconds
+pub fn format_entries(entries: &[SlowlogEntry]) -> String {
+    if entries.is_empty() {
+        return "(empty slowlog)".to_string();
+    }
+
+    let mut entries = entries.to_vec();
+    entries.sort_by(|a, b| b.micros.cmp(&a.micros).then(a.id.cmp(&b.id)));
+
+    let header = ["id", "time", "ms", "client", "name", "command"].map(String::from);
+    let rows: Vec<Vec<String>> = std::iter::once(header.to_vec())
+        .chain(entries.iter().map(|entry| {
+            vec![
+                entry.id.to_string(),
+                local_time(entry.timestamp),
+                format!("{:.3}", entry.micros as f64 / 1000.0),
+                entry.client_addr.clone(),
+                entry.client_name.clone(),
+                entry.args.join(" "),
+            ]
+        }))
+        .collect();
+    // ids and durations right aligned
+    output::format_columns(&rows, &[0, 2])
+}
+
+fn local_time(timestamp: i64) -> String {
+    let time = UNIX_EPOCH + Duration::from_secs(timestamp.max(0) as u64);
+    DateTime::<Local>::from(time)
+        .format("%Y-%m-%d %H:%M:%S")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::byte_buffer::BytesBuffer;
+
+    fn decode(bytes: &[u8]) -> RespType {
+        let mut buff = BytesBuffer::new(64);
+        buff.put_u8_slice(bytes);
+        RespType::decode(&mut buff)
+    }
+
+    /// `SLOWLOG GET 2` from redis 7, the second entry as sent by redis 3
+    const SLOWLOG: &[u8] = b"*2\r\n\
+        *6\r\n:14\r\n:1700000000\r\n:15000\r\n*3\r\n$3\r\nSET\r\n$3\r\nkey\r\n$5\r\na\nb c\r\n$15\r\n127.0.0.1:52614\r\n$3\r\napp\r\n\
+        *4\r\n:13\r\n:1699999999\r\n:20500\r\n*1\r\n$8\r\nFLUSHALL\r\n";
+
+    #[test]
+    fn slowlog_entries_are_parsed() {
+        let entries = parse_entries(&decode(SLOWLOG));
+        assert_eq!(
+            entries,
+            [
+                SlowlogEntry {
+                    id: 14,
+                    timestamp: 1700000000,
+                    micros: 15000,
+                    args: vec![
+                        "\"SET\"".to_string(),
+                        "\"key\"".to_string(),
+                        "\"a\\nb c\"".to_string()
+                    ],
+                    client_addr: "127.0.0.1:52614".to_string(),
+                    client_name: "app".to_string(),
+                },
+                SlowlogEntry {
+                    id: 13,
+                    timestamp: 1699999999,
+                    micros: 20500,
+                    args: vec!["\"FLUSHALL\"".to_string()],
+                    client_addr: String::new(),
+                    client_name: String::new(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed_entries_are_skipped() {
+        assert!(parse_entries(&decode(b"-ERR no\r\n")).is_empty());
+        assert!(parse_entries(&decode(b"*1\r\n*2\r\n:1\r\n:2\r\n")).is_empty());
+    }
+
+    #[test]
+    fn the_slowest_entry_comes_first() {
+        let table = format_entries(&parse_entries(&decode(SLOWLOG)));
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("id  time"));
+        assert!(lines[1].starts_with("13  "));
+        assert!(lines[1].contains("20.500"));
+        assert!(lines[2].contains("15.000  127.0.0.1:52614  app"));
+        assert_eq!(format_entries(&[]), "(empty slowlog)");
+    }
+}
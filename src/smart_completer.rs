@@ -1,17 +1,27 @@
-use crate::command_cache::CommandCache;
+use crate::{color, command_cache::CommandCache, live_completion::LiveCompletion};
 use rustyline::{
-    completion::Completer, highlight::Highlighter, hint::Hinter, validate::Validator, Context,
-    Result,
+    Context, Result, completion::Completer, highlight::Highlighter, hint::Hinter,
+    validate::Validator,
+};
+use std::{
+    borrow::Cow,
+    sync::{Arc, Mutex},
 };
-use std::sync::{Arc, Mutex};
 
 pub struct SmartCompleter {
     cache: Arc<Mutex<CommandCache>>,
+    /// fields and members read from the server, with `--live-completion`
+    live: Option<LiveCompletion>,
 }
 
 impl SmartCompleter {
     pub fn new(cache: Arc<Mutex<CommandCache>>) -> Self {
-        Self { cache }
+        Self { cache, live: None }
+    }
+
+    pub fn with_live_completion(mut self, live: Option<LiveCompletion>) -> Self {
+        self.live = live;
+        self
     }
 
     fn parse_command_line(&self, line: &str) -> (String, Vec<String>, usize) {
@@ -38,11 +48,6 @@ impl SmartCompleter {
         cache.get_matching_commands(prefix)
     }
 
-    fn get_key_completions(&self, prefix: &str) -> Vec<String> {
-        let cache = self.cache.lock().unwrap();
-        cache.get_matching_keys(prefix)
-    }
-
     fn get_parameter_completions(
         &self,
         command: &str,
@@ -52,6 +57,23 @@ impl SmartCompleter {
     ) -> Vec<String> {
         let cache = self.cache.lock().unwrap();
 
+        // 容器命令（CONFIG、CLIENT、XGROUP等）的子命令来自 COMMAND DOCS
+        if current_pos == 0 {
+            let subcommands = cache.get_subcommands(command);
+            if !subcommands.is_empty() {
+                return subcommands;
+            }
+        }
+
+        // SELECT 的数据库编号来自 CONFIG GET databases
+        if command == "SELECT" {
+            return if current_pos == 0 {
+                cache.database_indexes()
+            } else {
+                Vec::new()
+            };
+        }
+
         if let Some(_cmd_info) = cache.get_command(command) {
             // 根据命令类型提供不同的参数补全
             match command {
@@ -59,36 +81,36 @@ impl SmartCompleter {
                 | "HDEL" | "HGETALL" => {
                     // 这些命令的第一个参数是key
                     if current_pos == 0 {
-                        return self.get_key_completions(prefix);
+                        return cache.get_matching_keys(prefix);
                     }
                 }
                 "LPUSH" | "RPUSH" | "LPOP" | "RPOP" | "LLEN" => {
                     // List相关命令
                     if current_pos == 0 {
-                        return self.get_key_completions(prefix);
+                        return cache.get_matching_keys(prefix);
                     }
                 }
                 "SADD" | "SREM" | "SMEMBERS" | "SCARD" => {
                     // Set相关命令
                     if current_pos == 0 {
-                        return self.get_key_completions(prefix);
+                        return cache.get_matching_keys(prefix);
                     }
                 }
                 "ZADD" | "ZREM" | "ZRANGE" | "ZCARD" => {
                     // Sorted Set相关命令
                     if current_pos == 0 {
-                        return self.get_key_completions(prefix);
+                        return cache.get_matching_keys(prefix);
                     }
                 }
                 "CONFIG" => {
-                    // CONFIG命令的子命令补全
+                    // CONFIG命令的子命令补全，服务器没有返回子命令时使用
                     if current_pos == 0 {
                         return vec![
                             "GET".to_string(),
                             "SET".to_string(),
                             "RESETSTAT".to_string(),
                         ];
-                    } else if current_pos == 1 && args.get(0).map(|s| s.as_str()) == Some("GET") {
+                    } else if current_pos == 1 && args.first().map(|s| s.as_str()) == Some("GET") {
                         return vec![
                             "*".to_string(),
                             "maxmemory".to_string(),
@@ -156,33 +178,25 @@ impl SmartCompleter {
                     ];
                 }
             }
-            "EXPIRE" => {
-                if args.len() == 1 {
-                    // EXPIRE命令的时间补全
-                    return vec![
-                        "60".to_string(),
-                        "300".to_string(),
-                        "3600".to_string(),
-                        "86400".to_string(),
-                    ];
-                }
+            "EXPIRE" if args.len() == 1 => {
+                // EXPIRE命令的时间补全
+                return vec![
+                    "60".to_string(),
+                    "300".to_string(),
+                    "3600".to_string(),
+                    "86400".to_string(),
+                ];
             }
-            "CONFIG" => {
-                if args.len() == 2 && args.get(0).map(|s| s.as_str()) == Some("SET") {
-                    // CONFIG SET的值补全
-                    match args.get(1).map(|s| s.as_str()) {
-                        Some("maxmemory") => {
-                            return vec![
-                                "1gb".to_string(),
-                                "512mb".to_string(),
-                                "256mb".to_string(),
-                            ]
-                        }
-                        Some("timeout") => {
-                            return vec!["300".to_string(), "600".to_string(), "0".to_string()]
-                        }
-                        _ => {}
+            "CONFIG" if args.len() == 2 && args.first().map(|s| s.as_str()) == Some("SET") => {
+                // CONFIG SET的值补全
+                match args.get(1).map(|s| s.as_str()) {
+                    Some("maxmemory") => {
+                        return vec!["1gb".to_string(), "512mb".to_string(), "256mb".to_string()];
+                    }
+                    Some("timeout") => {
+                        return vec!["300".to_string(), "600".to_string(), "0".to_string()];
                     }
+                    _ => {}
                 }
             }
             _ => {}
@@ -212,8 +226,8 @@ impl Completer for SmartCompleter {
 
         let current_input = &line[start..pos];
 
-        let completions = if command.is_empty() {
-            // 没有输入命令，提供命令补全
+        let completions = if start == 0 {
+            // 还在输入命令，提供命令补全
             self.get_command_completions(current_input)
         } else {
             // 有命令，提供参数补全
@@ -221,8 +235,15 @@ impl Completer for SmartCompleter {
                 // 第一个参数，通常是key
                 self.get_parameter_completions(&command, &args, current_pos, current_input)
             } else {
-                // 后续参数，可能是值或选项
-                let mut completions = self.get_value_completions(&command, &args, current_input);
+                // 后续参数，可能是 hash 字段、集合成员、值或选项
+                let mut completions = self
+                    .live
+                    .as_ref()
+                    .map(|live| live.complete(&command, &args, current_pos))
+                    .unwrap_or_default();
+                if completions.is_empty() {
+                    completions = self.get_value_completions(&command, &args, current_input);
+                }
 
                 if completions.is_empty() {
                     // 如果没有特定的值补全，尝试参数补全
@@ -233,22 +254,133 @@ impl Completer for SmartCompleter {
             }
         };
 
-        // 过滤匹配当前输入的补全项
+        // 过滤匹配当前输入的补全项，命令和子命令不区分大小写
+        let input_upper = current_input.to_uppercase();
         let filtered: Vec<String> = completions
             .into_iter()
-            .filter(|candidate: &String| candidate.starts_with(current_input))
+            .filter(|candidate: &String| {
+                candidate.starts_with(current_input)
+                    || candidate.to_uppercase().starts_with(&input_upper)
+            })
             .collect();
 
+        // 命令前缀没有匹配时，尝试模糊匹配（如 hgtall => HGETALL）
+        if filtered.is_empty() && start == 0 && !current_input.is_empty() {
+            let cache = self.cache.lock().unwrap();
+            return Ok((start, cache.get_fuzzy_commands(current_input)));
+        }
+
         Ok((start, filtered))
     }
 }
 
 impl Hinter for SmartCompleter {
     type Hint = String;
+
+    fn hint(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> Option<String> {
+        // 只在行尾且刚输入空格时提示剩余参数
+        if pos < line.len() || !line.ends_with(' ') {
+            return None;
+        }
+
+        let (command, args, _) = self.parse_command_line(line);
+        if command.is_empty() {
+            return None;
+        }
+
+        let cache = self.cache.lock().unwrap();
+        cache.get_argument_hint(&command, args.len())
+    }
 }
 
-impl Highlighter for SmartCompleter {}
+impl Highlighter for SmartCompleter {
+    fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
+        // 灰色显示参数提示
+        Cow::Owned(color::paint(hint, color::GRAY))
+    }
+}
 
 impl Validator for SmartCompleter {}
 
 impl rustyline::Helper for SmartCompleter {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_cache::tests::{argument, cache, command};
+
+    fn completer(cache: CommandCache) -> SmartCompleter {
+        SmartCompleter::new(Arc::new(Mutex::new(cache)))
+    }
+
+    #[test]
+    fn container_commands_complete_their_subcommands() {
+        let mut config = command("CONFIG", -2, &[], 0);
+        config.subcommands = vec![
+            "config|set".to_string(),
+            "config|get".to_string(),
+            "config|rewrite".to_string(),
+        ];
+        let mut xgroup = command("XGROUP", -2, &[], 0);
+        xgroup.subcommands = vec!["xgroup|create".to_string(), "xgroup|destroy".to_string()];
+        let completer = completer(cache(vec![config, xgroup]));
+
+        assert_eq!(
+            completer.get_parameter_completions("CONFIG", &[], 0, ""),
+            ["GET", "REWRITE", "SET"]
+        );
+        assert_eq!(
+            completer.get_parameter_completions("XGROUP", &[], 0, ""),
+            ["CREATE", "DESTROY"]
+        );
+    }
+
+    #[test]
+    fn config_falls_back_without_subcommands_from_the_server() {
+        let completer = completer(cache(vec![command("CONFIG", -2, &[], 0)]));
+        assert_eq!(
+            completer.get_parameter_completions("CONFIG", &[], 0, ""),
+            ["GET", "SET", "RESETSTAT"]
+        );
+    }
+
+    #[test]
+    fn select_completes_the_database_indexes() {
+        let mut cache = cache(vec![command("SELECT", 2, &[], 0)]);
+        cache.set_databases(Some(16));
+        let completer = completer(cache);
+
+        let indexes = completer.get_parameter_completions("SELECT", &[], 0, "");
+        assert_eq!(indexes.len(), 16);
+        assert_eq!(indexes[0], "0");
+        assert_eq!(indexes[15], "15");
+        assert!(
+            completer
+                .get_parameter_completions("SELECT", &["1".to_string()], 1, "")
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn hints_are_gray_or_bare() {
+        let completer = completer(CommandCache::new());
+        let hint = completer.highlight_hint("key [key ...]");
+        assert_eq!(hint, color::paint("key [key ...]", color::GRAY));
+        assert_eq!(color::strip(&hint), "key [key ...]");
+    }
+
+    #[test]
+    fn hints_cover_the_arguments_not_yet_typed() {
+        let mut set = command("SET", -3, &["write"], 1);
+        set.arguments = vec![argument("key", "key"), argument("value", "string")];
+        let completer = completer(cache(vec![set]));
+        let history = rustyline::history::DefaultHistory::new();
+        let ctx = Context::new(&history);
+
+        let hint = |line: &str| completer.hint(line, line.len(), &ctx);
+        assert_eq!(hint("set ").as_deref(), Some("key value"));
+        assert_eq!(hint("SET k ").as_deref(), Some("value"));
+        assert_eq!(hint("SET k v "), None);
+        assert_eq!(hint("SET k"), None);
+    }
+}
@@ -0,0 +1,354 @@
+use anyhow::bail;
+
+/// split a command line into arguments the way redis-cli does: double quotes
+/// take `\"`, `\n`, `\r`, `\t`, `\b`, `\a` and `\xHH` escapes, single quotes only `\'`
+pub fn split_args(line: &str) -> anyhow::Result<Vec<Vec<u8>>> {
+    let bytes = line.as_bytes();
+    let mut args = Vec::new();
+    let mut i = 0;
+
+    loop {
+        // skip blanks
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        if i == bytes.len() {
+            return Ok(args);
+        }
+
+        let mut arg = Vec::new();
+        while i < bytes.len() && !bytes[i].is_ascii_whitespace() {
+            match bytes[i] {
+                b'"' => i = double_quoted(bytes, i + 1, &mut arg)?,
+                b'\'' => i = single_quoted(bytes, i + 1, &mut arg)?,
+                byte => {
+                    arg.push(byte);
+                    i += 1;
+                }
+            }
+        }
+        args.push(arg);
+    }
+}
+
+/// a trailing `> file` or `>> file` on a REPL line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redirect {
+    pub path: String,
+    /// `>>`: add to the end of the file instead of replacing it
+    pub append: bool,
+}
+
+/// split a trailing redirection off a line, `GET k > out.txt` gives `GET k`; only a
+/// bare unquoted `>` or `>>` followed by a single file name at the end of the line counts,
+/// so `SET k "a > b"`, `SET k a>b`, `ACL SETUSER bob on >secret` and
+/// `XREADGROUP ... STREAMS s >` are left alone
+pub fn split_redirect(line: &str) -> anyhow::Result<(&str, Option<Redirect>)> {
+    let bytes = line.as_bytes();
+    let mut skipped = Vec::new();
+    let mut spans = Vec::new();
+    let mut i = 0;
+
+    loop {
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        if i == bytes.len() {
+            break;
+        }
+        let start = i;
+        while i < bytes.len() && !bytes[i].is_ascii_whitespace() {
+            match bytes[i] {
+                b'"' => i = double_quoted(bytes, i + 1, &mut skipped)?,
+                b'\'' => i = single_quoted(bytes, i + 1, &mut skipped)?,
+                _ => i += 1,
+            }
+        }
+        spans.push(start..i);
+    }
+
+    // the command name itself is never a redirect
+    let [_, .., op, target] = spans.as_slice() else {
+        return Ok((line, None));
+    };
+    if !matches!(&line[op.clone()], ">" | ">>") {
+        return Ok((line, None));
+    }
+    // a single token always splits into a single argument
+    let path = split_args(&line[target.clone()])?.remove(0);
+    let path = String::from_utf8(path)
+        .map_err(|_| anyhow::anyhow!("Invalid redirection: file name is not UTF-8"))?;
+    let append = op.len() == 2;
+    Ok((line[..op.start].trim_end(), Some(Redirect { path, append })))
+}
+
+/// quote a single argument so that `split_args` reads it back unchanged
+pub fn quote_arg(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && !arg
+            .bytes()
+            .any(|byte| byte.is_ascii_whitespace() || matches!(byte, b'"' | b'\'' | b'\\'));
+    if plain {
+        return arg.to_string();
+    }
+
+    let mut quoted = String::with_capacity(arg.len() + 2);
+    quoted.push('"');
+    for c in arg.chars() {
+        if c == '"' || c == '\\' {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// `CONFIG SET` parameters holding a password
+const SECRET_PARAMETERS: [&str; 4] = [
+    "masterauth",
+    "requirepass",
+    "tls-key-file-pass",
+    "tls-client-key-file-pass",
+];
+
+/// commands carrying credentials, kept out of the history file and the `--debug` trace
+/// like redis-cli does: `AUTH`, `ACL SETUSER`, `HELLO` or `MIGRATE` with `AUTH`/`AUTH2`,
+/// and `CONFIG SET` of a password
+pub fn is_sensitive(args: &[impl AsRef<[u8]>]) -> bool {
+    let is = |arg: &[u8], words: &[&str]| {
+        words
+            .iter()
+            .any(|word| arg.eq_ignore_ascii_case(word.as_bytes()))
+    };
+    let Some((name, rest)) = args.split_first() else {
+        return false;
+    };
+    let name = name.as_ref();
+    let second = rest.first().map(|arg| arg.as_ref()).unwrap_or_default();
+
+    if is(name, &["AUTH"]) {
+        true
+    } else if is(name, &["ACL"]) {
+        is(second, &["SETUSER"])
+    } else if is(name, &["HELLO", "MIGRATE"]) {
+        rest.iter().any(|arg| is(arg.as_ref(), &["AUTH", "AUTH2"]))
+    } else if is(name, &["CONFIG"]) && is(second, &["SET"]) {
+        rest.iter().any(|arg| is(arg.as_ref(), &SECRET_PARAMETERS))
+    } else {
+        false
+    }
+}
+
+/// read a double quoted part starting after the quote, returns the position after the closing one
+fn double_quoted(bytes: &[u8], mut i: usize, arg: &mut Vec<u8>) -> anyhow::Result<usize> {
+    loop {
+        match bytes.get(i) {
+            None => bail!("Invalid argument(s): unbalanced quotes"),
+            Some(b'"') => return closing_quote(bytes, i + 1),
+            Some(b'\\') if bytes.get(i + 1) == Some(&b'x') => {
+                match bytes.get(i + 2..i + 4).and_then(hex_byte) {
+                    Some(byte) => {
+                        arg.push(byte);
+                        i += 4;
+                    }
+                    // not a valid hex escape, keep it literally
+                    None => {
+                        arg.push(b'x');
+                        i += 2;
+                    }
+                }
+            }
+            Some(b'\\') if i + 1 < bytes.len() => {
+                arg.push(match bytes[i + 1] {
+                    b'n' => b'\n',
+                    b'r' => b'\r',
+                    b't' => b'\t',
+                    b'b' => 0x08,
+                    b'a' => 0x07,
+                    other => other,
+                });
+                i += 2;
+            }
+            Some(&byte) => {
+                arg.push(byte);
+                i += 1;
+            }
+        }
+    }
+}
+
+/// read a single quoted part starting after the quote, returns the position after the closing one
+fn single_quoted(bytes: &[u8], mut i: usize, arg: &mut Vec<u8>) -> anyhow::Result<usize> {
+    loop {
+        match bytes.get(i) {
+            None => bail!("Invalid argument(s): unbalanced quotes"),
+            Some(b'\'') => return closing_quote(bytes, i + 1),
+            Some(b'\\') if bytes.get(i + 1) == Some(&b'\'') => {
+                arg.push(b'\'');
+                i += 2;
+            }
+            Some(&byte) => {
+                arg.push(byte);
+                i += 1;
+            }
+        }
+    }
+}
+
+/// a closing quote must be followed by a blank or the end of line
+fn closing_quote(bytes: &[u8], i: usize) -> anyhow::Result<usize> {
+    match bytes.get(i) {
+        Some(byte) if !byte.is_ascii_whitespace() => {
+            bail!("Invalid argument(s): closing quote must be followed by a space")
+        }
+        _ => Ok(i),
+    }
+}
+
+fn hex_byte(digits: &[u8]) -> Option<u8> {
+    u8::from_str_radix(std::str::from_utf8(digits).ok()?, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sensitive(line: &str) -> bool {
+        is_sensitive(&split_args(line).unwrap())
+    }
+
+    fn words(line: &str) -> Vec<String> {
+        split_args(line)
+            .unwrap()
+            .into_iter()
+            .map(|arg| String::from_utf8(arg).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn quoted_spaces_stay_in_one_argument() {
+        assert_eq!(
+            words("  SET  key \"big world\"  "),
+            ["SET", "key", "big world"]
+        );
+        assert_eq!(words("SET key 'big world'"), ["SET", "key", "big world"]);
+        assert_eq!(words("SET key \"\""), ["SET", "key", ""]);
+        assert!(words("").is_empty());
+    }
+
+    #[test]
+    fn escapes_inside_quotes() {
+        assert_eq!(words(r#"SET k "say \"hi\"""#), ["SET", "k", "say \"hi\""]);
+        assert_eq!(words(r"SET k 'it\'s'"), ["SET", "k", "it's"]);
+        assert_eq!(words(r#"SET k "a\nb\tc""#), ["SET", "k", "a\nb\tc"]);
+        // single quotes keep other backslashes
+        assert_eq!(words(r"SET k 'a\nb'"), ["SET", "k", r"a\nb"]);
+    }
+
+    #[test]
+    fn hex_escapes_produce_raw_bytes() {
+        assert_eq!(
+            split_args(r#"SET k "\x00\xff\x41""#).unwrap()[2],
+            [0x00, 0xFF, b'A']
+        );
+        // not hex, kept literally
+        assert_eq!(split_args(r#"SET k "\xzz""#).unwrap()[2], b"xzz");
+    }
+
+    #[test]
+    fn unbalanced_or_glued_quotes_are_errors() {
+        assert!(split_args("SET k \"open").is_err());
+        assert!(split_args("SET k 'open").is_err());
+        assert!(split_args("SET k \"a\"b").is_err());
+    }
+
+    #[test]
+    fn quoted_args_read_back_unchanged() {
+        for arg in [
+            "plain",
+            "",
+            "big world",
+            "say \"hi\"",
+            "back\\slash",
+            "it's",
+        ] {
+            let quoted = quote_arg(arg);
+            assert_eq!(split_args(&quoted).unwrap(), [arg.as_bytes()], "{}", quoted);
+        }
+        assert_eq!(quote_arg("plain"), "plain");
+        assert_eq!(quote_arg("a b"), "\"a b\"");
+    }
+
+    #[test]
+    fn a_trailing_redirect_is_split_off() {
+        assert_eq!(split_redirect("GET k").unwrap(), ("GET k", None));
+        assert_eq!(
+            split_redirect("GET k > out.txt").unwrap(),
+            (
+                "GET k",
+                Some(Redirect {
+                    path: "out.txt".to_string(),
+                    append: false
+                })
+            )
+        );
+        assert_eq!(
+            split_redirect("KEYS * >> \"my keys.txt\"").unwrap(),
+            (
+                "KEYS *",
+                Some(Redirect {
+                    path: "my keys.txt".to_string(),
+                    append: true
+                })
+            )
+        );
+    }
+
+    #[test]
+    fn a_quoted_or_embedded_gt_is_not_a_redirect() {
+        assert_eq!(split_redirect("SET k \"a > b\"").unwrap().1, None);
+        assert_eq!(split_redirect("SET k a>b").unwrap().1, None);
+        assert_eq!(split_redirect("GET k >out.txt").unwrap().1, None);
+        // the command name itself is never a redirect
+        assert_eq!(split_redirect(">").unwrap().1, None);
+        assert_eq!(split_redirect("> out.txt").unwrap().1, None);
+    }
+
+    #[test]
+    fn a_gt_argument_is_not_a_redirect() {
+        let line = "XREADGROUP GROUP g c STREAMS s >";
+        assert_eq!(split_redirect(line).unwrap(), (line, None));
+        let line = "ACL SETUSER bob on >secret";
+        assert_eq!(split_redirect(line).unwrap(), (line, None));
+        assert_eq!(split_redirect("GET k >").unwrap().1, None);
+        assert_eq!(split_redirect("GET k > a b").unwrap().1, None);
+    }
+
+    #[test]
+    fn an_unbalanced_quote_after_a_redirect_is_an_error() {
+        assert!(split_redirect("GET k > \"open").is_err());
+    }
+
+    #[test]
+    fn commands_with_credentials_are_sensitive() {
+        assert!(sensitive("auth secret"));
+        assert!(sensitive("AUTH user secret"));
+        assert!(sensitive("acl setuser bob on >pass"));
+        assert!(sensitive("HELLO 3 AUTH default secret"));
+        assert!(sensitive(
+            "MIGRATE host 6379 \"\" 0 5000 AUTH2 user pass KEYS a"
+        ));
+        assert!(sensitive("config set requirepass secret"));
+    }
+
+    #[test]
+    fn other_commands_are_not_sensitive() {
+        assert!(!sensitive("GET auth"));
+        assert!(!sensitive("HELLO 3"));
+        assert!(!sensitive("ACL LIST"));
+        assert!(!sensitive("CONFIG SET maxmemory 1gb"));
+        assert!(!sensitive("CONFIG GET requirepass"));
+        assert!(!is_sensitive(&[] as &[&[u8]]));
+    }
+}
@@ -0,0 +1,102 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+};
+
+use crate::{color, history};
+
+/// records the interactive session (prompts, commands and replies) to a file
+pub struct Transcript {
+    path: String,
+    file: File,
+}
+
+impl Transcript {
+    pub fn open(path: &str) -> anyhow::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            path: path.to_string(),
+            file,
+        })
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// record a prompt and the line typed after it, commands with credentials are left out
+    pub fn record_input(&mut self, prompt: &str, line: &str) {
+        if history::is_sensitive(line) {
+            let _ = writeln!(self.file, "{}(command with credentials, not shown)", prompt);
+        } else {
+            let _ = writeln!(self.file, "{}{}", prompt, escape_non_printable(line));
+        }
+    }
+
+    /// record a formatted reply or message, without its colors
+    pub fn record_output(&mut self, text: &str) {
+        let _ = writeln!(self.file, "{}", escape_non_printable(&color::strip(text)));
+    }
+}
+
+/// escape control bytes so binary replies don't corrupt the transcript
+fn escape_non_printable(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\n' | '\t' => escaped.push(c),
+            '\r' => escaped.push_str("\\r"),
+            c if c.is_control() => escaped.push_str(&format!("\\x{:02x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_and_replies_are_recorded() {
+        let path =
+            std::env::temp_dir().join(format!("rredis_cli_transcript_{}", std::process::id()));
+        let path = path.to_str().unwrap();
+        let _ = std::fs::remove_file(path);
+
+        let mut transcript = Transcript::open(path).unwrap();
+        transcript.record_input("127.0.0.1:6379> ", "GET k");
+        transcript.record_output("\x1b[32m\"a\x01b\r\"\x1b[0m");
+        drop(transcript);
+
+        let text = std::fs::read_to_string(path).unwrap();
+        let _ = std::fs::remove_file(path);
+        assert_eq!(text, "127.0.0.1:6379> GET k\n\"a\\x01b\\r\"\n");
+    }
+
+    #[test]
+    fn credentials_are_not_recorded() {
+        let path = std::env::temp_dir().join(format!(
+            "rredis_cli_transcript_secret_{}",
+            std::process::id()
+        ));
+        let path = path.to_str().unwrap();
+        let _ = std::fs::remove_file(path);
+
+        let mut transcript = Transcript::open(path).unwrap();
+        transcript.record_input("> ", "AUTH default secret");
+        transcript.record_input("> ", "CONFIG SET requirepass secret");
+        drop(transcript);
+
+        let text = std::fs::read_to_string(path).unwrap();
+        let _ = std::fs::remove_file(path);
+        assert!(!text.contains("secret"));
+        assert_eq!(text.lines().count(), 2);
+    }
+
+    #[test]
+    fn newlines_and_tabs_are_kept() {
+        assert_eq!(escape_non_printable("1) a\n2)\tb"), "1) a\n2)\tb");
+        assert_eq!(escape_non_printable("\x00\x7f"), "\\x00\\x7f");
+    }
+}